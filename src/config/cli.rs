@@ -1,4 +1,4 @@
-use chrono::{Datelike, Local};
+use chrono::{Datelike, Days, Local, NaiveDate};
 use clap::{
     crate_authors, crate_description, crate_name, crate_version, value_parser, Arg, ArgAction,
     ArgMatches, Command,
@@ -22,6 +22,14 @@ pub fn init_cli() -> ArgMatches {
                 .default_missing_value("true")
                 .help("Enable interactive train timetable search. No value required.")
         )
+        .arg(
+            Arg::new("tui")
+                .required(false)
+                .long("tui")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["from", "to", "search", "again"])
+                .help("Use a full-screen terminal UI for the interactive train timetable search. No value required.")
+        )
         .arg(
             Arg::new("search")
                 .required(false)
@@ -37,8 +45,8 @@ pub fn init_cli() -> ArgMatches {
                 .short('f')
                 .long("from")
                 .env("RODALIES_CLI_FROM")
-                .action(ArgAction::Set)
-                .help("The origin's station ID.")
+                .action(ArgAction::Append)
+                .help("The origin's station ID. Can be repeated to rank the journeys from several origins by arrival.")
         )
         .arg(
             Arg::new("to")
@@ -46,8 +54,8 @@ pub fn init_cli() -> ArgMatches {
                 .short('t')
                 .long("to")
                 .env("RODALIES_CLI_TO")
-                .action(ArgAction::Set)
-                .help("The destinations's station ID.")
+                .action(ArgAction::Append)
+                .help("The destinations's station ID. Can be repeated to rank the journeys to several destinations by arrival.")
         )
         .arg(
             Arg::new("day")
@@ -72,11 +80,513 @@ pub fn init_cli() -> ArgMatches {
                 .long("year")
                 .action(ArgAction::Set)
                 .help("The year value of the date to search for (default = today's year).")
+        )
+        .arg(
+            Arg::new("date")
+                .required(false)
+                .long("date")
+                .action(ArgAction::Set)
+                .conflicts_with_all(["day", "month", "year"])
+                .help("The date to search for, as dd/mm/yyyy, yyyy-mm-dd, today or tomorrow, instead of its day, month and year (default = today).")
+        )
+        .arg(
+            Arg::new("sort")
+                .required(false)
+                .long("sort")
+                .action(ArgAction::Set)
+                .value_parser(["departure", "arrival", "duration", "transfers"])
+                .conflicts_with_all(["watch", "notify-cmd", "notify-before"])
+                .help("Sort the journeys by the given field (default = the rodalies site order).")
+        )
+        .arg(
+            Arg::new("via")
+                .required(false)
+                .long("via")
+                .action(ArgAction::Append)
+                .conflicts_with_all(["arrive-by", "watch", "notify-cmd", "notify-before"])
+                .help("Stop at the given station ID or name on the way, at least the given minutes (STATION[=STOP], default = 5m). Can be repeated.")
+        )
+        .arg(
+            Arg::new("arrive-by")
+                .required(false)
+                .long("arrive-by")
+                .action(ArgAction::Set)
+                .conflicts_with_all(["watch", "notify-cmd", "notify-before"])
+                .help("List the latest journeys arriving by the given time (HH:MM).")
+        )
+        .arg(
+            Arg::new("buffer")
+                .required(false)
+                .long("buffer")
+                .action(ArgAction::Set)
+                .requires("arrive-by")
+                .help("When arriving by a time, the margin to arrive before it, like 10m or 1h (default = 0m).")
+        )
+        .args(filter_args())
+        .arg(
+            Arg::new("tight-transfer")
+                .required(false)
+                .long("tight-transfer")
+                .action(ArgAction::Set)
+                .conflicts_with_all(["watch", "notify-cmd", "notify-before"])
+                .help("Warn about the transfers leaving less than the given time to change trains, like 8m (default = 5m).")
+        )
+        .arg(
+            Arg::new("again")
+                .required(false)
+                .short('a')
+                .long("again")
+                .num_args(0..=1)
+                .default_missing_value("1")
+                .value_parser(value_parser!(usize))
+                .help("Repeat the last search, or the given option of the search history.")
+        )
+        .args(watch_args())
+        .subcommand(
+            Command::new("history")
+                .about("List your recent searches, to repeat them with '--again'.")
+        )
+        .subcommand(
+            Command::new("next")
+                .about("Show the next departures of a trip from now on.")
+                .args(trip_args())
+                .arg(
+                    Arg::new("count")
+                        .required(false)
+                        .short('n')
+                        .long("count")
+                        .action(ArgAction::Set)
+                        .default_value("3")
+                        .value_parser(value_parser!(usize))
+                        .help("The number of departures to show.")
+                )
+                .arg(
+                    Arg::new("output")
+                        .required(false)
+                        .short('o')
+                        .long("output")
+                        .action(ArgAction::Set)
+                        .default_value("table")
+                        .value_parser(["table", "text", "waybar"])
+                        .help("The output format: a table, a single line of text for status bars or waybar's JSON.")
+                )
+                .args(filter_args())
+                .args(watch_args())
+        )
+        .subcommand(
+            Command::new("first")
+                .about("Show the first journey of the day of a trip.")
+                .args(trip_args())
+                .args(first_last_args())
+        )
+        .subcommand(
+            Command::new("last")
+                .about("Show the last journey of the day of a trip, warning when it leaves before your going home time.")
+                .args(trip_args())
+                .args(first_last_args())
+                .arg(
+                    Arg::new("home-time")
+                        .required(false)
+                        .long("home-time")
+                        .env("RODALIES_CLI_HOME_TIME")
+                        .action(ArgAction::Set)
+                        .help("Warn when the last journey leaves before the given going home time (HH:MM).")
+                )
+        )
+        .subcommand(
+            Command::new("batch")
+                .about("Search the timetables of many queries from a file, or from stdin, at once.")
+                .arg(
+                    Arg::new("file")
+                        .required(false)
+                        .action(ArgAction::Set)
+                        .help("The queries file: one 'from, to[, date[, hour]]' or favourite trip per line, JSON or TOML (default = stdin).")
+                )
+                .arg(
+                    Arg::new("format")
+                        .required(false)
+                        .long("format")
+                        .action(ArgAction::Set)
+                        .value_parser(["lines", "json", "toml"])
+                        .help("The queries file format (default = the file extension's, or lines).")
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .required(false)
+                        .short('j')
+                        .long("concurrency")
+                        .action(ArgAction::Set)
+                        .default_value("4")
+                        .value_parser(value_parser!(u64).range(1..=16))
+                        .help("How many queries are requested at the same time.")
+                )
+                .arg(
+                    Arg::new("output")
+                        .required(false)
+                        .short('o')
+                        .long("output")
+                        .action(ArgAction::Set)
+                        .default_value("table")
+                        .value_parser(["table", "json", "csv"])
+                        .help("The output format: a table per query, JSON or a single CSV table.")
+                )
+        )
+        .subcommand(
+            Command::new("matrix")
+                .about("Show the fastest journey duration and transfers between every pair of the given stations.")
+                .arg(
+                    Arg::new("stations")
+                        .required(true)
+                        .num_args(2..)
+                        .action(ArgAction::Set)
+                        .help("The stations' IDs or names.")
+                )
+                .arg(
+                    Arg::new("date")
+                        .required(false)
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .help("The date to search for, as dd/mm/yyyy, yyyy-mm-dd, today or tomorrow (default = today).")
+                )
+                .args(window_args())
+                .args(politeness_args())
+                .arg(
+                    Arg::new("output")
+                        .required(false)
+                        .short('o')
+                        .long("output")
+                        .action(ArgAction::Set)
+                        .default_value("table")
+                        .value_parser(["table", "csv"])
+                        .help("The output format: a matrix table, or CSV with a row per origin and destination.")
+                )
+        )
+        .subcommand(
+            Command::new("week")
+                .about("Show the trains of a trip on 7 consecutive days side by side, highlighting the ones that only run on some days.")
+                .args(trip_args())
+                .arg(
+                    Arg::new("date")
+                        .required(false)
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .help("The first date to compare, as dd/mm/yyyy, yyyy-mm-dd, today or tomorrow (default = today).")
+                )
+                .args(window_args())
+                .args(politeness_args())
+                .arg(
+                    Arg::new("output")
+                        .required(false)
+                        .short('o')
+                        .long("output")
+                        .action(ArgAction::Set)
+                        .default_value("table")
+                        .value_parser(["table", "csv"])
+                        .help("The output format: a table or CSV.")
+                )
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Show the trains of a trip added, removed or retimed since its stored snapshot, or between two dates.")
+                .args(trip_args())
+                .arg(
+                    Arg::new("date")
+                        .required(false)
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .help("The date to compare, as dd/mm/yyyy, yyyy-mm-dd, today or tomorrow (default = today).")
+                )
+                .arg(
+                    Arg::new("against")
+                        .required(false)
+                        .short('a')
+                        .long("against")
+                        .action(ArgAction::Set)
+                        .help("Another date to compare the timetable of the date with, instead of its stored snapshot.")
+                )
+                .arg(
+                    Arg::new("output")
+                        .required(false)
+                        .short('o')
+                        .long("output")
+                        .action(ArgAction::Set)
+                        .default_value("table")
+                        .value_parser(["table", "json"])
+                        .help("The output format: a table or JSON.")
+                )
+        )
+        .subcommand(
+            Command::new("reach")
+                .about("Show where one can get from an origin, with the earliest arrival, duration and transfers to every station.")
+                .arg(
+                    Arg::new("origin")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .help("The origin's station ID or name.")
+                )
+                .arg(
+                    Arg::new("date")
+                        .required(false)
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .help("The date to search for, as dd/mm/yyyy, yyyy-mm-dd, today or tomorrow (default = today).")
+                )
+                .args(window_args())
+                .arg(
+                    Arg::new("within")
+                        .required(false)
+                        .long("within")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(i64).range(1..))
+                        .help("Only the stations reached within the given minutes.")
+                )
+                .arg(
+                    Arg::new("sort")
+                        .required(false)
+                        .long("sort")
+                        .action(ArgAction::Set)
+                        .default_value("duration")
+                        .value_parser(["duration", "arrival", "transfers", "name"])
+                        .help("The field to sort the stations by.")
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .required(false)
+                        .long("refresh")
+                        .action(ArgAction::SetTrue)
+                        .help("Request again the timetables cached by previous runs. No value required.")
+                )
+                .args(politeness_args())
+                .arg(
+                    Arg::new("output")
+                        .required(false)
+                        .short('o')
+                        .long("output")
+                        .action(ArgAction::Set)
+                        .default_value("table")
+                        .value_parser(["table", "csv", "json"])
+                        .help("The output format: a table, CSV or JSON.")
+                )
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve the stations, timetables and next departures as JSON over HTTP or stdio.")
+                .arg(
+                    Arg::new("host")
+                        .required(false)
+                        .long("host")
+                        .action(ArgAction::Set)
+                        .default_value("127.0.0.1")
+                        .help("The address to listen on.")
+                )
+                .arg(
+                    Arg::new("port")
+                        .required(false)
+                        .short('p')
+                        .long("port")
+                        .env("RODALIES_CLI_PORT")
+                        .action(ArgAction::Set)
+                        .default_value("3000")
+                        .value_parser(value_parser!(u16))
+                        .help("The port to listen on.")
+                )
+                .arg(
+                    Arg::new("cache-ttl")
+                        .required(false)
+                        .long("cache-ttl")
+                        .action(ArgAction::Set)
+                        .default_value("300")
+                        .value_parser(value_parser!(u64))
+                        .help("How many seconds a requested timetable is kept in memory before requesting it again.")
+                )
+                .arg(
+                    Arg::new("stdio")
+                        .required(false)
+                        .long("stdio")
+                        .action(ArgAction::SetTrue)
+                        .help("Answer JSON-RPC requests read line by line from stdin instead of HTTP ones. No value required.")
+                )
         );
 
     cli.get_matches()
 }
 
+/// Configures the arguments that leave out journeys by their transfers and lines.
+fn filter_args() -> [Arg; 6] {
+    [
+        Arg::new("direct")
+            .required(false)
+            .long("direct")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("max-transfers")
+            .help("List only the journeys without transfers. No value required."),
+        Arg::new("max-transfers")
+            .required(false)
+            .long("max-transfers")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u64))
+            .help("List only the journeys with at most the given transfers."),
+        Arg::new("min-transfer")
+            .required(false)
+            .long("min-transfer")
+            .action(ArgAction::Set)
+            .help("List only the journeys leaving at least the given time to change trains, like 5m."),
+        Arg::new("max-wait")
+            .required(false)
+            .long("max-wait")
+            .action(ArgAction::Set)
+            .help("List only the journeys waiting at most the given time at any transfer, like 20m."),
+        Arg::new("line")
+            .required(false)
+            .long("line")
+            .action(ArgAction::Append)
+            .help("List only the journeys taking the given train line, like R2 or R2 Nord. Can be repeated."),
+        Arg::new("exclude-line")
+            .required(false)
+            .long("exclude-line")
+            .action(ArgAction::Append)
+            .help("Leave out the journeys taking the given train line. Can be repeated."),
+    ]
+}
+
+/// Configures the arguments of the watch mode, that keeps refreshing the departures, and of the departure reminders.
+fn watch_args() -> [Arg; 5] {
+    [
+        Arg::new("watch")
+            .required(false)
+            .short('w')
+            .long("watch")
+            .action(ArgAction::Set)
+            .num_args(0..=1)
+            .default_missing_value("60")
+            .value_parser(value_parser!(u64))
+            .help("Keep watching the departures, refreshing them every given seconds (default = 60)."),
+        Arg::new("bell")
+            .required(false)
+            .long("bell")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(i64).range(0..))
+            .help("When watching, ring the terminal bell the given minutes before the chosen departure."),
+        Arg::new("departure")
+            .required(false)
+            .long("departure")
+            .action(ArgAction::Set)
+            .help("The departure time (HH:MM) to ring the bell or to be reminded of (default = the next one)."),
+        Arg::new("notify-cmd")
+            .required(false)
+            .long("notify-cmd")
+            .action(ArgAction::Set)
+            .help("Run the given shell command as reminder before the chosen departure, with the journey fields as RODALIES_* environment variables."),
+        Arg::new("notify-before")
+            .required(false)
+            .long("notify-before")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(i64).range(0..))
+            .help("How many minutes before the chosen departure the reminder command is run (default = 5). Without '--notify-cmd', RODALIES_CLI_NOTIFY_CMD is run."),
+    ]
+}
+
+/// Configures the arguments of the departure window of the journeys searched.
+fn window_args() -> [Arg; 2] {
+    [
+        Arg::new("after")
+            .required(false)
+            .long("after")
+            .action(ArgAction::Set)
+            .help("Only journeys departing at or after the given time (HH:MM) (default = 00:00)."),
+        Arg::new("before")
+            .required(false)
+            .long("before")
+            .action(ArgAction::Set)
+            .help("Only journeys departing at or before the given time (HH:MM) (default = 23:59)."),
+    ]
+}
+
+/// Configures the arguments that limit how many requests are sent to the rodalies site, and how often.
+fn politeness_args() -> [Arg; 2] {
+    [
+        Arg::new("concurrency")
+            .required(false)
+            .short('j')
+            .long("concurrency")
+            .action(ArgAction::Set)
+            .default_value("2")
+            .value_parser(value_parser!(u64).range(1..=8))
+            .help("How many requests are sent to the rodalies site at the same time."),
+        Arg::new("delay")
+            .required(false)
+            .long("delay")
+            .action(ArgAction::Set)
+            .default_value("500")
+            .value_parser(value_parser!(u64))
+            .help("How many milliseconds to wait before each request, to be polite with the rodalies site."),
+    ]
+}
+
+/// Configures the arguments that select the trip of a subcommand: a saved favourite or the origin and destination stations.
+fn trip_args() -> [Arg; 4] {
+    [
+        Arg::new("trip")
+            .required(false)
+            .action(ArgAction::Set)
+            .help("The name of a saved favourite trip."),
+        Arg::new("from")
+            .required(false)
+            .short('f')
+            .long("from")
+            .env("RODALIES_CLI_FROM")
+            .action(ArgAction::Set)
+            .help("The origin's station ID or name."),
+        Arg::new("to")
+            .required(false)
+            .short('t')
+            .long("to")
+            .env("RODALIES_CLI_TO")
+            .action(ArgAction::Set)
+            .help("The destinations's station ID or name."),
+        Arg::new("save")
+            .required(false)
+            .long("save")
+            .action(ArgAction::Set)
+            .help("Save the trip as a favourite with the given name."),
+    ]
+}
+
+/// Configures the arguments shared by the `first` and `last` subcommands: the date, the transfers and the output.
+fn first_last_args() -> [Arg; 3] {
+    [
+        Arg::new("date")
+            .required(false)
+            .long("date")
+            .action(ArgAction::Set)
+            .help("The date to search for, as dd/mm/yyyy, yyyy-mm-dd, today or tomorrow (default = today)."),
+        Arg::new("max-transfers")
+            .required(false)
+            .long("max-transfers")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u64))
+            .help("Only consider the journeys with at most the given transfers."),
+        Arg::new("output")
+            .required(false)
+            .short('o')
+            .long("output")
+            .action(ArgAction::Set)
+            .default_value("table")
+            .value_parser(["table", "json"])
+            .help("The output format: a table or JSON."),
+    ]
+}
+
+/// Given a container of CLI args, it tells whether the output is meant for other programs, like status bars or stdio clients.
+/// In such case nothing else than the results must be printed.
+pub fn quiet_mode(args: &ArgMatches) -> bool {
+    args.subcommand().is_some_and(|(_, sub_args)| {
+        let output = sub_args.try_get_one::<String>("output").ok().flatten();
+        let stdio = sub_args.try_get_one::<bool>("stdio").ok().flatten();
+        output.is_some_and(|output| output != "table") || stdio == Some(&true)
+    })
+}
+
 /// Configures and returns the Table to print results from.
 pub fn init_results_table() -> Table {
     let mut results_table = Table::new();
@@ -114,8 +624,22 @@ pub fn parse_trip(args: &ArgMatches) -> Result<(String, String), Box<dyn Error>>
     Ok((from.unwrap().to_string(), to.unwrap().to_string()))
 }
 
-/// Given a container of CLI args, it processes the `day`, `month` and `year` arguments.
+/// Given a container of CLI args, it tells whether any of the `date`, `day`, `month` and `year` arguments is provided.
+pub fn has_date(args: &ArgMatches) -> bool {
+    args.contains_id("date")
+        || args.contains_id("day")
+        || args.contains_id("month")
+        || args.contains_id("year")
+}
+
+/// Given a container of CLI args, it processes the `date`, or the `day`, `month` and `year` arguments.
 pub fn parse_date(args: &ArgMatches) -> Result<String, Box<dyn Error>> {
+    if let Some(date) = args.get_one::<String>("date") {
+        let date = format_date(&parse_query_date(Some(date))?);
+        println!("🔍 Searching timetable for date {}", date);
+        return Ok(date);
+    }
+
     let dt = Local::now();
     let day = match args.get_one::<String>("day") {
         Some(day) => match day.parse::<u32>() {
@@ -147,9 +671,57 @@ pub fn parse_date(args: &ArgMatches) -> Result<String, Box<dyn Error>> {
     Ok(format!("{:02}/{:02}/{}", day, month, year))
 }
 
+/// Parses a date given as `dd/mm/yyyy` or as `yyyy-mm-dd`.
+pub fn parse_param_date(date: &str) -> Result<NaiveDate, Box<dyn Error>> {
+    NaiveDate::parse_from_str(date, "%d/%m/%Y")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+        .map_err(|_| "🚨 Please, specify the date as dd/mm/yyyy or yyyy-mm-dd".into())
+}
+
+/// Parses the date of a query, as `dd/mm/yyyy`, `yyyy-mm-dd`, `today` or `tomorrow` (default = today).
+pub fn parse_query_date(date: Option<&str>) -> Result<NaiveDate, Box<dyn Error>> {
+    let today = Local::now().date_naive();
+    match date {
+        None | Some("") | Some("today") => Ok(today),
+        Some("tomorrow") => Ok(today + Days::new(1)),
+        Some(date) => parse_param_date(date),
+    }
+}
+
+/// Parses an amount of minutes given like `10`, `10m`, `1h` or `1h30m`.
+pub fn parse_minutes(value: &str) -> Result<i64, Box<dyn Error>> {
+    let error = || {
+        format!(
+            "🚨 Please, specify '{}' as minutes, like 10m or 1h30m",
+            value
+        )
+    };
+    let value = value.trim().to_lowercase();
+    if !value.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(error().into());
+    }
+    let (hours, minutes) = match value.split_once('h') {
+        Some((hours, minutes)) => (hours, minutes),
+        None => ("0", value.as_str()),
+    };
+    let minutes = minutes.strip_suffix('m').unwrap_or(minutes);
+    let hours = i64::from(hours.parse::<u32>().map_err(|_| error())?);
+    let minutes = match minutes {
+        "" => 0,
+        minutes => i64::from(minutes.parse::<u32>().map_err(|_| error())?),
+    };
+    Ok(hours * 60 + minutes)
+}
+
+/// Formats a date the way the rodalies site expects it on searches (`dd/mm/yyyy`).
+pub fn format_date(date: &NaiveDate) -> String {
+    format!("{:02}/{:02}/{}", date.day(), date.month(), date.year())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{init_cli, init_results_table};
+    use super::{format_date, init_cli, init_results_table, parse_minutes, parse_query_date};
+    use chrono::{Days, Local, NaiveDate};
 
     #[test]
     fn test_init_results_table_is_empty() {
@@ -160,10 +732,42 @@ mod tests {
     #[test]
     fn test_init_cli_with_defaults() {
         let args = init_cli();
-        assert_eq!(args.ids().len(), 1);
+        assert_eq!(args.ids().len(), 3);
         assert_eq!(
             args.ids().map(|id| id.as_str()).collect::<Vec<_>>(),
-            ["interactive"]
+            ["interactive", "tui", "direct"]
         );
     }
+
+    #[test]
+    fn test_format_date_pads_day_and_month() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+        assert_eq!(format_date(&date), "07/03/2024");
+    }
+
+    #[test]
+    fn test_parse_query_date() {
+        let today = Local::now().date_naive();
+        assert_eq!(parse_query_date(None).unwrap(), today);
+        assert_eq!(
+            parse_query_date(Some("tomorrow")).unwrap(),
+            today + Days::new(1)
+        );
+        let date = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+        assert_eq!(parse_query_date(Some("07/03/2024")).unwrap(), date);
+        assert_eq!(parse_query_date(Some("2024-03-07")).unwrap(), date);
+        assert!(parse_query_date(Some("someday")).is_err());
+    }
+
+    #[test]
+    fn test_parse_minutes() {
+        assert_eq!(parse_minutes("10").unwrap(), 10);
+        assert_eq!(parse_minutes("10m").unwrap(), 10);
+        assert_eq!(parse_minutes("1h").unwrap(), 60);
+        assert_eq!(parse_minutes("1h30m").unwrap(), 90);
+        assert_eq!(parse_minutes("2h05").unwrap(), 125);
+        assert!(parse_minutes("m").is_err());
+        assert!(parse_minutes("ten").is_err());
+        assert!(parse_minutes("-5m").is_err());
+    }
 }
@@ -7,3 +7,6 @@ pub mod cli;
 
 /// `check` provides the methods to check and inform about latest published online version of the `rodalies-cli` and the one being used by the user.
 pub mod check;
+
+/// `store` provides the methods to load and save the data files kept by `rodalies-cli`, such as the search history.
+pub mod store;
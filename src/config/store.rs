@@ -0,0 +1,42 @@
+use clap::crate_name;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{env, error::Error, fs, io::ErrorKind, path::PathBuf};
+
+/// Returns the directory where `rodalies-cli` keeps its data files.
+///
+/// It is `RODALIES_CLI_DATA_DIR` if set, otherwise the platform's user data directory.
+pub fn data_dir() -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(dir) = env::var_os("RODALIES_CLI_DATA_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return Ok(PathBuf::from(dir).join(crate_name!()));
+    }
+    if let Some(dir) = env::var_os("APPDATA") {
+        return Ok(PathBuf::from(dir).join(crate_name!()));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        return Ok(PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join(crate_name!()));
+    }
+    Err("🚨 Could not find where to store data, please set RODALIES_CLI_DATA_DIR".into())
+}
+
+/// Loads the JSON data file with the given name, or its default value if it does not exist yet.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T, Box<dyn Error>> {
+    match fs::read_to_string(data_dir()?.join(name)) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Saves the value as a JSON data file with the given name.
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
@@ -2,9 +2,19 @@ use chrono::{Datelike, Local};
 use std::error::Error;
 
 use rodalies_cli::config::check::check_rodalies_version;
-use rodalies_cli::config::cli::{init_cli, interactive_mode};
+use rodalies_cli::config::cli::{init_cli, interactive_mode, quiet_mode};
+use rodalies_cli::rodalies::batch::search_batch;
 use rodalies_cli::rodalies::client::init_client;
+use rodalies_cli::rodalies::ends::{search_day_end, DayEnd};
+use rodalies_cli::rodalies::history::{search_again, search_history};
 use rodalies_cli::rodalies::interactive::search_interactive;
+use rodalies_cli::rodalies::matrix::search_matrix;
+use rodalies_cli::rodalies::next::search_next;
+use rodalies_cli::rodalies::reach::search_reach;
+use rodalies_cli::rodalies::serve::serve;
+use rodalies_cli::rodalies::snapshot::search_diff;
+use rodalies_cli::rodalies::tui::search_tui;
+use rodalies_cli::rodalies::week::search_week;
 use rodalies_cli::rodalies::{station::search_station, timetable::search_timetable};
 
 #[tokio::main]
@@ -12,17 +22,43 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let args = init_cli();
     let client = init_client();
     let dt = Local::now();
+    // the full-screen UI would wipe anything printed before it
+    let tui = args.get_flag("tui");
 
-    println!(
-        "🚂 Rodalies CLI 📅 Today's date is {:02}/{:02}/{}",
-        dt.day(),
-        dt.month(),
-        dt.year()
-    );
+    if !quiet_mode(&args) && !tui {
+        println!(
+            "🚂 Rodalies CLI 📅 Today's date is {:02}/{:02}/{}",
+            dt.day(),
+            dt.month(),
+            dt.year()
+        );
 
-    check_rodalies_version().await;
+        check_rodalies_version().await;
+    }
+
+    match args.subcommand() {
+        Some(("history", _)) => return search_history(),
+        Some(("next", next_args)) => return search_next(&client, next_args).await,
+        Some(("first", first_args)) => {
+            return search_day_end(&client, first_args, DayEnd::First).await
+        }
+        Some(("last", last_args)) => return search_day_end(&client, last_args, DayEnd::Last).await,
+        Some(("batch", batch_args)) => return search_batch(&client, batch_args).await,
+        Some(("matrix", matrix_args)) => return search_matrix(&client, matrix_args).await,
+        Some(("week", week_args)) => return search_week(&client, week_args).await,
+        Some(("diff", diff_args)) => return search_diff(&client, diff_args).await,
+        Some(("reach", reach_args)) => return search_reach(&client, reach_args).await,
+        Some(("serve", serve_args)) => return serve(&client, serve_args).await,
+        _ => {}
+    }
+
+    if args.contains_id("again") {
+        return search_again(&client, &args).await;
+    }
 
-    if !interactive_mode(&args).unwrap() {
+    if tui {
+        search_tui(&client).await?
+    } else if !interactive_mode(&args).unwrap() {
         if args.contains_id("search") {
             // search station
             search_station(&client, &args).await?
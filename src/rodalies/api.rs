@@ -0,0 +1,336 @@
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, Timelike};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, parse_param_date},
+    rodalies::{
+        next::{upcoming_departures, NextDeparture},
+        station::{fuzzy_search_stations, get_stations_list, resolve_station, Station},
+        timetable::{get_timetable, journey_json, timetable_json, Timetable},
+    },
+};
+
+/// A query answered by the API, with its parameters already validated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiRequest {
+    /// The stations fuzzy matching the query, or all of them.
+    Stations { query: Option<String> },
+    /// The timetable of a trip on the given date, from the given hour (`HH`).
+    Timetable {
+        from: String,
+        to: String,
+        date: NaiveDate,
+        hour: String,
+    },
+    /// The next `count` departures of a trip from now on.
+    Next {
+        from: String,
+        to: String,
+        count: usize,
+    },
+}
+
+impl ApiRequest {
+    /// Builds the request of the given name (`stations`, `timetable` or `next`) from its parameters.
+    pub fn parse(name: &str, params: &HashMap<String, String>) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "stations" => Ok(ApiRequest::Stations {
+                query: params.get("q").cloned(),
+            }),
+            "timetable" => Ok(ApiRequest::Timetable {
+                from: required_param(params, "from")?,
+                to: required_param(params, "to")?,
+                date: match params.get("date") {
+                    Some(date) => parse_param_date(date)?,
+                    None => Local::now().date_naive(),
+                },
+                hour: match params.get("hour") {
+                    Some(hour) => match hour.parse::<u32>() {
+                        Ok(hour) if hour < 24 => format!("{:02}", hour),
+                        _ => return Err("🚨 Please, specify the hour as HH".into()),
+                    },
+                    None => "00".to_string(),
+                },
+            }),
+            "next" => Ok(ApiRequest::Next {
+                from: required_param(params, "from")?,
+                to: required_param(params, "to")?,
+                count: match params.get("count") {
+                    Some(count) => match count.parse::<usize>() {
+                        Ok(count) if count > 0 => count,
+                        _ => return Err("🚨 Please, specify a count of at least one".into()),
+                    },
+                    None => 3,
+                },
+            }),
+            _ => Err(format!("🚨 Unknown request '{}'", name).into()),
+        }
+    }
+}
+
+/// Returns the value of a mandatory parameter.
+fn required_param(params: &HashMap<String, String>, name: &str) -> Result<String, Box<dyn Error>> {
+    match params.get(name) {
+        Some(value) if !value.is_empty() => Ok(value.to_string()),
+        _ => Err(format!("🚨 Please, specify the '{}' parameter", name).into()),
+    }
+}
+
+/// Formats the next departure as JSON, with its countdown.
+pub fn next_departure_json(departure: &NextDeparture, now: NaiveDateTime) -> Value {
+    json!({
+        "departure": departure.departure.format("%Y-%m-%dT%H:%M").to_string(),
+        "minutes_left": departure.minutes_left(now),
+        "journey": journey_json(&departure.journey, departure.departure.date()),
+    })
+}
+
+/// The key of a cached timetable: the origin and destination IDs, the date and the hour.
+type TimetableKey = (String, String, NaiveDate, String);
+
+/// Answers the API requests as JSON, keeping the stations list and the requested timetables in memory.
+///
+/// It can be shared by concurrent requests: the cache is only locked to read or update it, never while requesting.
+pub struct Api {
+    client: Client,
+    /// How long a requested timetable is kept before requesting it again.
+    ttl: Duration,
+    stations: Mutex<Option<Arc<Vec<Station>>>>,
+    timetables: Mutex<HashMap<TimetableKey, (Instant, Timetable)>>,
+}
+
+impl Api {
+    pub fn new(client: Client, ttl: Duration) -> Self {
+        Api {
+            client,
+            ttl,
+            stations: Mutex::new(None),
+            timetables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the stations list, requesting it only until it is got once.
+    pub async fn stations_list(&self) -> Result<Arc<Vec<Station>>, Box<dyn Error>> {
+        let cached = self
+            .stations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(stations) = cached {
+            return Ok(stations);
+        }
+
+        let stations = Arc::new(get_stations_list(&self.client).await?);
+        *self.stations.lock().unwrap_or_else(PoisonError::into_inner) = Some(stations.clone());
+        Ok(stations)
+    }
+
+    /// Finds the station matching the given ID or name.
+    async fn station(&self, value: &str) -> Result<Station, Box<dyn Error>> {
+        resolve_station(&self.stations_list().await?, value)
+    }
+
+    /// Returns the timetable of the trip, requesting it only if not cached or older than the TTL.
+    pub async fn timetable(
+        &self,
+        from: &Station,
+        to: &Station,
+        date: NaiveDate,
+        hour: &str,
+    ) -> Result<Timetable, Box<dyn Error>> {
+        let key = (from.id.clone(), to.id.clone(), date, hour.to_string());
+        let cached = self
+            .timetables
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+            .map(|(_, timetable)| timetable.clone());
+        if let Some(timetable) = cached {
+            return Ok(timetable);
+        }
+
+        let timetable = get_timetable(
+            &self.client,
+            from.id.clone(),
+            to.id.clone(),
+            format_date(&date),
+            hour.to_string(),
+        )
+        .await?;
+        let ttl = self.ttl;
+        let mut timetables = self
+            .timetables
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        timetables.retain(|_, (fetched, _)| fetched.elapsed() < ttl);
+        timetables.insert(key, (Instant::now(), timetable.clone()));
+        Ok(timetable)
+    }
+
+    /// Returns the next `count` departures of the trip from `now` on, looking at tomorrow's timetable if needed.
+    pub async fn next_departures(
+        &self,
+        from: &Station,
+        to: &Station,
+        now: NaiveDateTime,
+        count: usize,
+    ) -> Result<Vec<NextDeparture>, Box<dyn Error>> {
+        let today = now.date();
+        // whole days are cached, so that every request of the same day hits the cache
+        let mut today_error = None;
+        let mut departures = match self.timetable(from, to, today, "00").await {
+            Ok(timetable) => upcoming_departures(&timetable.journeys, today, now),
+            Err(error) => {
+                // kept as text, so that the request can be answered from any thread
+                today_error = Some(error.to_string());
+                Vec::new()
+            }
+        };
+
+        if departures.len() < count {
+            let tomorrow = today + Days::new(1);
+            match self.timetable(from, to, tomorrow, "00").await {
+                Ok(timetable) => {
+                    departures.extend(upcoming_departures(&timetable.journeys, tomorrow, now))
+                }
+                Err(error) if departures.is_empty() => {
+                    return Err(today_error.map_or(error, |error| error.into()))
+                }
+                Err(..) => {}
+            }
+        }
+
+        departures.truncate(count);
+        Ok(departures)
+    }
+
+    /// Answers the request as JSON.
+    pub async fn answer(&self, request: ApiRequest) -> Result<Value, Box<dyn Error>> {
+        match request {
+            ApiRequest::Stations { query } => {
+                let stations_list = self.stations_list().await?;
+                Ok(match query {
+                    Some(query) => json!(fuzzy_search_stations(&stations_list, &query)),
+                    None => json!(*stations_list),
+                })
+            }
+            ApiRequest::Timetable {
+                from,
+                to,
+                date,
+                hour,
+            } => {
+                let from = self.station(&from).await?;
+                let to = self.station(&to).await?;
+                let timetable = self.timetable(&from, &to, date, &hour).await?;
+                Ok(json!({
+                    "from": from,
+                    "to": to,
+                    "date": format_date(&date),
+                    "hour": hour,
+                    "timetable": timetable_json(&timetable, date),
+                }))
+            }
+            ApiRequest::Next { from, to, count } => {
+                let from = self.station(&from).await?;
+                let to = self.station(&to).await?;
+                let now = Local::now().naive_local();
+                let now = now.with_second(0).unwrap_or(now);
+                let departures = self.next_departures(&from, &to, now, count).await?;
+                Ok(json!({
+                    "from": from,
+                    "to": to,
+                    "departures": departures
+                        .iter()
+                        .map(|departure| next_departure_json(departure, now))
+                        .collect::<Vec<_>>(),
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::{
+        next::dated_departures,
+        timetable::{fixture_date, fixture_timetable},
+    };
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_api_requests() {
+        assert_eq!(
+            ApiRequest::parse("stations", &params(&[("q", "sants")])).unwrap(),
+            ApiRequest::Stations {
+                query: Some("sants".to_string())
+            }
+        );
+        assert_eq!(
+            ApiRequest::parse(
+                "timetable",
+                &params(&[("from", "79300"), ("to", "Sitges"), ("date", "2024-03-07")])
+            )
+            .unwrap(),
+            ApiRequest::Timetable {
+                from: "79300".to_string(),
+                to: "Sitges".to_string(),
+                date: fixture_date(),
+                hour: "00".to_string(),
+            }
+        );
+        assert_eq!(
+            ApiRequest::parse("next", &params(&[("from", "79300"), ("to", "79202")])).unwrap(),
+            ApiRequest::Next {
+                from: "79300".to_string(),
+                to: "79202".to_string(),
+                count: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_api_requests_rejects_wrong_params() {
+        assert!(ApiRequest::parse("next", &params(&[("from", "79300")])).is_err());
+        assert!(ApiRequest::parse(
+            "next",
+            &params(&[("from", "1"), ("to", "2"), ("count", "0")])
+        )
+        .is_err());
+        assert!(ApiRequest::parse(
+            "timetable",
+            &params(&[("from", "1"), ("to", "2"), ("date", "7.3.24")])
+        )
+        .is_err());
+        assert!(ApiRequest::parse("unknown", &params(&[])).is_err());
+    }
+
+    #[test]
+    fn test_next_departure_json() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+        let departures = dated_departures(&timetable.journeys, date);
+        let now = date.and_hms_opt(5, 54, 0).unwrap();
+
+        let json = next_departure_json(&departures[0], now);
+        assert_eq!(json["departure"], "2024-03-07T06:04");
+        assert_eq!(json["minutes_left"], 10);
+        assert_eq!(json["journey"]["legs"][1]["train"], "R2");
+        assert_eq!(json["journey"]["waits"][0], "5 min");
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::{Duration, NaiveDate, NaiveTime, Timelike};
+use clap::ArgMatches;
+use std::error::Error;
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, parse_minutes},
+    rodalies::{
+        filter::JourneyFilter,
+        timetable::{get_timetable, parse_time, Journey, Timetable},
+    },
+};
+
+/// How many of the latest journeys arriving on time are listed.
+const ARRIVE_BY_COUNT: usize = 3;
+
+/// How many hours earlier the search starts again when not enough journeys arrive on time.
+const HOURS_STEP: u32 = 2;
+
+/// Given a container of CLI args, it processes the `arrive-by` and `buffer` arguments into the arrival time and the buffer minutes.
+pub fn parse_arrive_by(args: &ArgMatches) -> Result<Option<(NaiveTime, i64)>, Box<dyn Error>> {
+    let arrive_by = match args.try_get_one::<String>("arrive-by") {
+        Ok(Some(arrive_by)) => match parse_time(arrive_by) {
+            Some(arrive_by) => arrive_by,
+            None => return Err("🚨 Please, specify the arrival time as HH:MM".into()),
+        },
+        _ => return Ok(None),
+    };
+    let buffer = match args.get_one::<String>("buffer") {
+        Some(buffer) => parse_minutes(buffer)?,
+        None => 0,
+    };
+
+    arrival_deadline(arrive_by, buffer)?;
+    Ok(Some((arrive_by, buffer)))
+}
+
+/// Returns the latest arrival time, the buffer minutes before the time to arrive by.
+pub fn arrival_deadline(arrive_by: NaiveTime, buffer: i64) -> Result<NaiveTime, Box<dyn Error>> {
+    let (deadline, overflow) = arrive_by.overflowing_sub_signed(Duration::minutes(buffer));
+    if overflow != 0 {
+        return Err("🚨 Please, specify a buffer that does not go back to the previous day".into());
+    }
+    Ok(deadline)
+}
+
+/// Returns the latest departing journeys of the date arriving at or before the deadline on that same date, in departure order.
+pub fn latest_arriving(
+    journeys: &[Journey],
+    date: NaiveDate,
+    deadline: NaiveTime,
+    count: usize,
+) -> Vec<Journey> {
+    let deadline = date.and_time(deadline);
+    // the searched start hours overlap, so the same journey might be found more than once
+    let mut on_time: Vec<Journey> = Vec::new();
+    for journey in journeys {
+        let arrives = journey
+            .arrival_at(date)
+            .is_some_and(|arrival| arrival <= deadline);
+        if arrives && !on_time.contains(journey) {
+            on_time.push(journey.clone());
+        }
+    }
+
+    on_time.sort_by_key(|journey| journey.departure_at(date));
+    let skip = on_time.len().saturating_sub(count);
+    on_time.split_off(skip)
+}
+
+/// Searches the timetable from the hour before the deadline, walking back to earlier start hours until enough journeys arrive on time.
+pub async fn get_arriving_by(
+    client: &Client,
+    from: &str,
+    to: &str,
+    date: NaiveDate,
+    deadline: NaiveTime,
+    filter: &JourneyFilter,
+) -> Result<Timetable, Box<dyn Error>> {
+    let mut hour = deadline.hour().saturating_sub(1);
+    let mut found: Vec<Journey> = Vec::new();
+    let mut last: Option<Timetable> = None;
+    let mut error = None;
+
+    loop {
+        // a start hour without trains is reported as an error by the site, so earlier ones are tried
+        match get_timetable(
+            client,
+            from.to_string(),
+            to.to_string(),
+            format_date(&date),
+            format!("{:02}", hour),
+        )
+        .await
+        {
+            Ok(timetable) => {
+                found.extend(
+                    timetable
+                        .journeys
+                        .iter()
+                        .filter(|journey| filter.matches(journey))
+                        .cloned(),
+                );
+                found = latest_arriving(&found, date, deadline, ARRIVE_BY_COUNT);
+                last = Some(timetable);
+            }
+            Err(fetch_error) => error = Some(fetch_error),
+        }
+
+        if found.len() >= ARRIVE_BY_COUNT || hour == 0 {
+            break;
+        }
+        hour = hour.saturating_sub(HOURS_STEP);
+    }
+
+    let last = match (last, error) {
+        (Some(last), _) if !found.is_empty() => last,
+        (None, Some(error)) => return Err(error.into()),
+        _ => {
+            return Err(format!(
+                "🚨 No journeys found arriving by {} on {}",
+                deadline.format("%H:%M"),
+                format_date(&date)
+            )
+            .into())
+        }
+    };
+    Ok(Timetable {
+        transfers: found
+            .iter()
+            .map(|journey| journey.transfers())
+            .max()
+            .unwrap_or_default(),
+        journeys: found,
+        min_temp: last.min_temp,
+        max_temp: last.max_temp,
+    })
+}
+
+/// Searches the latest journeys arriving at the destination by the deadline, to be displayed as any other timetable.
+pub async fn search_arrive_by(
+    client: &Client,
+    from: String,
+    to: String,
+    date: NaiveDate,
+    deadline: NaiveTime,
+    filter: &JourneyFilter,
+) -> Result<Timetable, Box<dyn Error>> {
+    println!(
+        "🎯 Searching the latest journeys arriving by {}",
+        deadline.format("%H:%M")
+    );
+    get_arriving_by(client, &from, &to, date, deadline, filter).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    #[test]
+    fn test_latest_arriving() {
+        let timetable = fixture_timetable();
+        let time = |time: &str| parse_time(time).unwrap();
+        let date = fixture_date();
+
+        let journeys = latest_arriving(&timetable.journeys, date, time("23:59"), 3);
+        assert_eq!(journeys.len(), 2);
+        assert_eq!(journeys[1].departure(), "21:19");
+
+        let journeys = latest_arriving(&timetable.journeys, date, time("23:59"), 1);
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].departure(), "21:19");
+
+        // 23:17 minus a 10 minutes buffer leaves only the morning journey
+        let journeys = latest_arriving(&timetable.journeys, date, time("23:07"), 3);
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].arrival(), "08:22");
+
+        assert!(latest_arriving(&timetable.journeys, date, time("08:00"), 3).is_empty());
+    }
+
+    #[test]
+    fn test_latest_arriving_drops_repeated_journeys() {
+        let timetable = fixture_timetable();
+        let time = |time: &str| parse_time(time).unwrap();
+        let date = fixture_date();
+
+        // another train leaving at the same time splits the repeated journey
+        let evening = timetable.journeys[1].clone();
+        let mut other = evening.clone();
+        other.legs[0].train = "R2".to_string();
+        let journeys = [evening.clone(), other, evening];
+
+        let journeys = latest_arriving(&journeys, date, time("23:59"), 3);
+        assert_eq!(journeys.len(), 2);
+    }
+}
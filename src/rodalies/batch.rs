@@ -0,0 +1,525 @@
+use chrono::NaiveDate;
+use clap::ArgMatches;
+use futures::{stream, StreamExt};
+use prettytable::{Cell, Row, Table};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    error::Error,
+    fs,
+    io::{stdin, stdout, Read},
+    path::Path,
+};
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, init_results_table, parse_query_date},
+    rodalies::{
+        station::{get_stations_list, is_station_id, resolve_station, Station},
+        timetable::{display_timetable, get_timetable, timetable_json, Timetable},
+        trip::get_favourite,
+    },
+};
+
+/// A query of a batch file, either a saved favourite trip or the origin and destination stations.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchQuery {
+    /// The name of a saved favourite trip.
+    pub trip: Option<String>,
+    /// The origin's station ID or name.
+    pub from: Option<String>,
+    /// The destination's station ID or name.
+    pub to: Option<String>,
+    /// The date, as `dd/mm/yyyy`, `yyyy-mm-dd`, `today` or `tomorrow` (default = today).
+    pub date: Option<String>,
+    /// The hour (`HH`) from which trains are listed (default = 00).
+    pub hour: Option<String>,
+}
+
+/// The TOML batch file layout, with a `[[query]]` table per query.
+#[derive(Debug, Deserialize)]
+struct TomlBatch {
+    query: Vec<BatchQuery>,
+}
+
+/// A batch query ready to be requested.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedQuery {
+    pub from: Station,
+    pub to: Station,
+    pub date: NaiveDate,
+    pub hour: String,
+}
+
+/// The outcome of a batch query, in the same order as given.
+pub struct BatchResult {
+    /// The query as given, to label it when it could not be resolved.
+    pub query: BatchQuery,
+    /// The resolved query, if it could be.
+    pub resolved: Option<ResolvedQuery>,
+    /// The timetable found, or why it could not be.
+    pub timetable: Result<Timetable, String>,
+}
+
+/// Parses the batch queries, given as JSON, TOML or one `from, to[, date[, hour]]` (or favourite `trip`) per line.
+pub fn parse_batch(content: &str, format: &str) -> Result<Vec<BatchQuery>, Box<dyn Error>> {
+    let queries = match format {
+        "json" => serde_json::from_str(content)
+            .map_err(|error| format!("🚨 Could not read the JSON queries: {}", error))?,
+        "toml" => {
+            toml::from_str::<TomlBatch>(content)
+                .map_err(|error| format!("🚨 Could not read the TOML queries: {}", error))?
+                .query
+        }
+        _ => parse_batch_lines(content)?,
+    };
+
+    if queries.is_empty() {
+        return Err("🚨 There are no queries to run".into());
+    }
+    Ok(queries)
+}
+
+/// Parses one query per line, skipping blank lines and `#` comments.
+fn parse_batch_lines(content: &str) -> Result<Vec<BatchQuery>, Box<dyn Error>> {
+    let mut queries = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<String> = line.split(',').map(|f| f.trim().to_string()).collect();
+        let query = match fields.as_slice() {
+            [trip] => BatchQuery {
+                trip: Some(trip.clone()),
+                ..Default::default()
+            },
+            [from, to, rest @ ..] if rest.len() <= 2 => BatchQuery {
+                from: Some(from.clone()),
+                to: Some(to.clone()),
+                date: rest.first().cloned(),
+                hour: rest.get(1).cloned(),
+                ..Default::default()
+            },
+            _ => {
+                return Err(format!(
+                    "🚨 Please, write line {} as 'from, to[, date[, hour]]' or as a favourite trip name",
+                    number + 1
+                )
+                .into())
+            }
+        };
+        queries.push(query);
+    }
+    Ok(queries)
+}
+
+/// Tells the format of the batch file: the given one, the one of the file extension, or JSON if it looks like it.
+pub fn batch_format(format: Option<&str>, path: Option<&str>, content: &str) -> String {
+    if let Some(format) = format {
+        return format.to_string();
+    }
+    match path
+        .and_then(|path| Path::new(path).extension())
+        .and_then(|extension| extension.to_str())
+    {
+        Some("json") => "json".to_string(),
+        Some("toml") => "toml".to_string(),
+        _ if content.trim_start().starts_with('[') => "json".to_string(),
+        _ => "lines".to_string(),
+    }
+}
+
+/// Parses the hour of a batch query.
+fn parse_query_hour(hour: Option<&str>) -> Result<String, Box<dyn Error>> {
+    match hour {
+        None | Some("") => Ok("00".to_string()),
+        Some(hour) => match hour.parse::<u32>() {
+            Ok(hour) if hour < 24 => Ok(format!("{:02}", hour)),
+            _ => Err(format!("🚨 Please, specify the hour '{}' as HH", hour).into()),
+        },
+    }
+}
+
+/// Resolves the stations, date and hour of a batch query.
+fn resolve_query(
+    stations_list: &[Station],
+    query: &BatchQuery,
+) -> Result<ResolvedQuery, Box<dyn Error>> {
+    let station = |value: &str| {
+        if stations_list.is_empty() {
+            // only IDs are given, there was no need to request the stations list
+            Ok(Station {
+                id: value.to_string(),
+                name: value.to_string(),
+            })
+        } else {
+            resolve_station(stations_list, value)
+        }
+    };
+
+    let (from, to) = match (&query.trip, &query.from, &query.to) {
+        (Some(trip), None, None) => {
+            let trip = get_favourite(trip)?;
+            (trip.from, trip.to)
+        }
+        (None, Some(from), Some(to)) => (station(from)?, station(to)?),
+        _ => {
+            return Err(
+                "🚨 Please, specify either a favourite trip or origin and destination stations"
+                    .into(),
+            )
+        }
+    };
+
+    Ok(ResolvedQuery {
+        from,
+        to,
+        date: parse_query_date(query.date.as_deref())?,
+        hour: parse_query_hour(query.hour.as_deref())?,
+    })
+}
+
+/// Resolves and requests the timetables of all queries, at most `concurrency` at a time, keeping their order.
+///
+/// The stations list is requested only once, and only if any station is given by name.
+pub async fn run_batch(
+    client: &Client,
+    queries: Vec<BatchQuery>,
+    concurrency: usize,
+) -> Result<Vec<BatchResult>, Box<dyn Error>> {
+    let needs_names = queries.iter().any(|query| {
+        [&query.from, &query.to]
+            .iter()
+            .any(|value| value.as_deref().is_some_and(|value| !is_station_id(value)))
+    });
+    let stations_list = if needs_names {
+        get_stations_list(client).await?
+    } else {
+        Vec::new()
+    };
+
+    let results = stream::iter(queries)
+        .map(|query| {
+            let resolved = resolve_query(&stations_list, &query);
+            async move {
+                match resolved {
+                    Ok(resolved) => {
+                        let timetable = get_timetable(
+                            client,
+                            resolved.from.id.clone(),
+                            resolved.to.id.clone(),
+                            format_date(&resolved.date),
+                            resolved.hour.clone(),
+                        )
+                        .await
+                        .map_err(|error| error.to_string());
+                        BatchResult {
+                            query,
+                            resolved: Some(resolved),
+                            timetable,
+                        }
+                    }
+                    Err(error) => BatchResult {
+                        query,
+                        resolved: None,
+                        timetable: Err(error.to_string()),
+                    },
+                }
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await;
+
+    Ok(results)
+}
+
+/// Returns a short description of the batch query, like `Girona → Sitges on 07/03/2024 from 08h`.
+pub fn describe_query(result: &BatchResult) -> String {
+    match &result.resolved {
+        Some(resolved) => format!(
+            "{} → {} on {} from {}h",
+            resolved.from.name,
+            resolved.to.name,
+            format_date(&resolved.date),
+            resolved.hour
+        ),
+        None => match &result.query.trip {
+            Some(trip) => format!("trip '{}'", trip),
+            None => format!(
+                "{} → {}",
+                result.query.from.as_deref().unwrap_or("?"),
+                result.query.to.as_deref().unwrap_or("?")
+            ),
+        },
+    }
+}
+
+/// Builds a single table with the journeys of all queries, one row per journey or failed query.
+pub fn build_batch_table(results: &[BatchResult]) -> Table {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Query"),
+        Cell::new("Origin"),
+        Cell::new("Destination"),
+        Cell::new("Date"),
+        Cell::new("Start"),
+        Cell::new("End"),
+        Cell::new("Duration"),
+        Cell::new("Train"),
+        Cell::new("Transfers"),
+        Cell::new("Error"),
+    ]));
+
+    for (index, result) in results.iter().enumerate() {
+        let (from, to, date) = match &result.resolved {
+            Some(resolved) => (
+                resolved.from.name.clone(),
+                resolved.to.name.clone(),
+                format_date(&resolved.date),
+            ),
+            None => (
+                result.query.from.clone().unwrap_or_default(),
+                result.query.to.clone().unwrap_or_default(),
+                result.query.date.clone().unwrap_or_default(),
+            ),
+        };
+        let query_cells = || {
+            vec![
+                Cell::new(&(index + 1).to_string()),
+                Cell::new(&from),
+                Cell::new(&to),
+                Cell::new(&date),
+            ]
+        };
+
+        match &result.timetable {
+            Ok(timetable) => {
+                for journey in timetable.journeys.iter() {
+                    let mut cells = query_cells();
+                    cells.extend([
+                        Cell::new(journey.departure()),
+                        Cell::new(&journey.arrival_label()),
+                        Cell::new(&journey.duration),
+                        Cell::new(&journey.trains()),
+                        Cell::new(&journey.transfers().to_string()),
+                        Cell::new(""),
+                    ]);
+                    results_table.add_row(Row::new(cells));
+                }
+            }
+            Err(error) => {
+                let mut cells = query_cells();
+                cells.extend([
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(error),
+                ]);
+                results_table.add_row(Row::new(cells));
+            }
+        }
+    }
+
+    results_table
+}
+
+/// Formats the batch results as a JSON array, with the query and its timetable or error.
+pub fn format_batch_json(results: &[BatchResult]) -> String {
+    let results: Vec<_> = results
+        .iter()
+        .map(|result| {
+            let query = match &result.resolved {
+                Some(resolved) => json!({
+                    "from": resolved.from,
+                    "to": resolved.to,
+                    "date": format_date(&resolved.date),
+                    "hour": resolved.hour,
+                }),
+                None => json!({
+                    "trip": result.query.trip,
+                    "from": result.query.from,
+                    "to": result.query.to,
+                    "date": result.query.date,
+                    "hour": result.query.hour,
+                }),
+            };
+            match (&result.timetable, &result.resolved) {
+                (Ok(timetable), Some(resolved)) => json!({
+                    "query": query,
+                    "timetable": timetable_json(timetable, resolved.date),
+                }),
+                (Ok(timetable), None) => json!({ "query": query, "timetable": timetable }),
+                (Err(error), _) => json!({ "query": query, "error": error }),
+            }
+        })
+        .collect();
+    serde_json::to_string_pretty(&results).unwrap_or_default()
+}
+
+/// Runs the queries of the batch file given by the CLI args, or of stdin, and displays their results.
+pub async fn search_batch(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let path = args
+        .get_one::<String>("file")
+        .map(|path| path.as_str())
+        .filter(|path| *path != "-");
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+    let concurrency = *args.get_one::<u64>("concurrency").unwrap_or(&4) as usize;
+
+    let content = match path {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|error| format!("🚨 Could not read the queries file '{}': {}", path, error))?,
+        None => {
+            let mut content = String::new();
+            stdin().read_to_string(&mut content)?;
+            content
+        }
+    };
+    let format = batch_format(
+        args.get_one::<String>("format").map(|f| f.as_str()),
+        path,
+        &content,
+    );
+    let queries = parse_batch(&content, &format)?;
+
+    if output == "table" {
+        println!(
+            "📋 Running {} queries, {} at a time",
+            queries.len(),
+            concurrency
+        );
+    }
+    let results = run_batch(client, queries, concurrency).await?;
+
+    match output {
+        "json" => println!("{}", format_batch_json(&results)),
+        "csv" => {
+            build_batch_table(&results).to_csv(stdout())?;
+        }
+        _ => {
+            for (index, result) in results.iter().enumerate() {
+                println!("\n🚆 {}. {}", index + 1, describe_query(result));
+                match &result.timetable {
+                    Ok(timetable) => display_timetable(timetable),
+                    Err(error) => println!("{}", error),
+                }
+            }
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|result| result.timetable.is_err())
+        .count();
+    if failed > 0 {
+        return Err(format!("🚨 {} of {} queries failed", failed, results.len()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    fn query(from: &str, to: &str) -> BatchQuery {
+        BatchQuery {
+            from: Some(from.to_string()),
+            to: Some(to.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_batch_formats() {
+        let lines = "# weekly plan\n79300, 79202, 07/03/2024, 8\n\nhome\n";
+        let queries = parse_batch(lines, "lines").unwrap();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].date.as_deref(), Some("07/03/2024"));
+        assert_eq!(queries[0].hour.as_deref(), Some("8"));
+        assert_eq!(queries[1].trip.as_deref(), Some("home"));
+        assert!(parse_batch("1, 2, 3, 4, 5", "lines").is_err());
+        assert!(parse_batch("# nothing", "lines").is_err());
+
+        let json = r#"[{"from": "79300", "to": "Sitges", "date": "tomorrow"}]"#;
+        assert_eq!(batch_format(None, None, json), "json");
+        let queries = parse_batch(json, "json").unwrap();
+        assert_eq!(queries[0].to.as_deref(), Some("Sitges"));
+
+        let toml = "[[query]]\ntrip = \"work\"\n\n[[query]]\nfrom = \"79300\"\nto = \"79202\"\n";
+        assert_eq!(batch_format(None, Some("plan.toml"), toml), "toml");
+        let queries = parse_batch(toml, "toml").unwrap();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[1], query("79300", "79202"));
+        assert!(parse_batch("[[query]]\norigin = \"1\"", "toml").is_err());
+    }
+
+    #[test]
+    fn test_resolve_query() {
+        let stations_list = vec![
+            Station {
+                id: "79300".to_string(),
+                name: "Girona".to_string(),
+            },
+            Station {
+                id: "71701".to_string(),
+                name: "Sitges".to_string(),
+            },
+        ];
+        let mut batch_query = query("79300", "sitges");
+        batch_query.date = Some("2024-03-07".to_string());
+        batch_query.hour = Some("8".to_string());
+
+        let resolved = resolve_query(&stations_list, &batch_query).unwrap();
+        assert_eq!(resolved.from.name, "Girona");
+        assert_eq!(resolved.to.id, "71701");
+        assert_eq!(resolved.date, fixture_date());
+        assert_eq!(resolved.hour, "08");
+
+        batch_query.hour = Some("25".to_string());
+        assert!(resolve_query(&stations_list, &batch_query).is_err());
+        assert!(resolve_query(&stations_list, &BatchQuery::default()).is_err());
+    }
+
+    fn batch_results() -> Vec<BatchResult> {
+        let resolved = resolve_query(&[], &query("79300", "71701")).unwrap();
+        vec![
+            BatchResult {
+                query: query("79300", "71701"),
+                resolved: Some(resolved),
+                timetable: Ok(fixture_timetable()),
+            },
+            BatchResult {
+                query: query("1", "2"),
+                resolved: None,
+                timetable: Err("🚨 No trains".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_build_batch_table() {
+        let table = build_batch_table(&batch_results());
+        assert_eq!(table.len(), 3);
+        assert_eq!(table[0][7].get_content(), "R11 → R2");
+        assert_eq!(table[1][4].get_content(), "21:19");
+        assert_eq!(table[2][0].get_content(), "2");
+        assert_eq!(table[2][9].get_content(), "🚨 No trains");
+    }
+
+    #[test]
+    fn test_format_batch_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&format_batch_json(&batch_results())).unwrap();
+        assert_eq!(json[0]["query"]["from"]["id"], "79300");
+        assert_eq!(json[0]["timetable"]["journeys"][1]["duration"], "01:58");
+        assert_eq!(json[1]["error"], "🚨 No trains");
+    }
+}
@@ -38,6 +38,7 @@ pub async fn get_timetable_page(
     from: String,
     to: String,
     date: String,
+    hour: String,
 ) -> Result<Html, Box<dyn Error>> {
     let mut response = client
         .post("/en/horaris")
@@ -50,8 +51,8 @@ pub async fn get_timetable_page(
         )
         .content_type("application/x-www-form-urlencoded")
         .body_string(format!(
-            "origen={}&desti={}&dataViatge={}&horaIni=00&lang=en&cercaRodalies=true&tornada=false",
-            from, to, date
+            "origen={}&desti={}&dataViatge={}&horaIni={}&lang=en&cercaRodalies=true&tornada=false",
+            from, to, date, hour
         ))
         .await?;
 
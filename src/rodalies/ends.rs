@@ -0,0 +1,209 @@
+use chrono::{Days, NaiveDate, NaiveTime, Timelike};
+use clap::ArgMatches;
+use serde_json::json;
+use std::error::Error;
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, parse_query_date},
+    rodalies::{
+        timetable::{
+            build_timetable_table, get_timetable, journey_json, parse_time, Journey, Timetable,
+        },
+        trip::parse_trip_input,
+    },
+};
+
+/// The hour before which a going home time is taken as past midnight, on the night after the searched date.
+const NIGHT_END: u32 = 5;
+
+/// Which end of the day is searched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DayEnd {
+    First,
+    Last,
+}
+
+/// Returns the first or last departing journey of the date, with at most the given transfers.
+pub fn pick_journey(
+    journeys: &[Journey],
+    date: NaiveDate,
+    end: DayEnd,
+    max_transfers: Option<usize>,
+) -> Option<&Journey> {
+    let journeys = journeys.iter().filter(|journey| {
+        let transfers = match max_transfers {
+            Some(max) => journey.transfers() <= max,
+            None => true,
+        };
+        journey.departure_at(date).is_some() && transfers
+    });
+    match end {
+        DayEnd::First => journeys.min_by_key(|journey| journey.departure_at(date)),
+        // the fastest one is taken when several leave at the same time
+        DayEnd::Last => journeys.max_by_key(|journey| {
+            (
+                journey.departure_at(date),
+                std::cmp::Reverse(journey.arrival_at(date)),
+            )
+        }),
+    }
+}
+
+/// Returns the warning when the last journey leaves before the going home time of the searched date.
+///
+/// Going home times before 05:00 are taken as the night after the date, so `00:30` is later than any evening train.
+pub fn build_home_warning(
+    journey: &Journey,
+    date: NaiveDate,
+    home_time: NaiveTime,
+) -> Option<String> {
+    let departure = journey.departure_at(date)?;
+    let mut home = date.and_time(home_time);
+    if home_time.hour() < NIGHT_END {
+        home = home.checked_add_days(Days::new(1))?;
+    }
+    if departure >= home {
+        return None;
+    }
+    Some(format!(
+        "⚠️ The last journey leaves at {}, before your going home time {}",
+        journey.departure(),
+        home_time.format("%H:%M")
+    ))
+}
+
+/// Displays the first or last journey of the day of the trip given by the CLI args, warning about the going home time on the last one.
+pub async fn search_day_end(
+    client: &Client,
+    args: &ArgMatches,
+    end: DayEnd,
+) -> Result<(), Box<dyn Error>> {
+    let date = parse_query_date(args.get_one::<String>("date").map(|date| date.as_str()))?;
+    let max_transfers = args
+        .get_one::<u64>("max-transfers")
+        .map(|max| *max as usize);
+    // the going home time is only given to the `last` subcommand
+    let home_time = match (end, args.try_get_one::<String>("home-time")) {
+        (DayEnd::Last, Ok(Some(home_time))) => match parse_time(home_time) {
+            Some(home_time) => Some(home_time),
+            None => return Err("🚨 Please, specify the going home time as HH:MM".into()),
+        },
+        _ => None,
+    };
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+    let trip = parse_trip_input(client, args).await?;
+
+    let timetable = get_timetable(
+        client,
+        trip.from.id.clone(),
+        trip.to.id.clone(),
+        format_date(&date),
+        "00".to_string(),
+    )
+    .await?;
+    let journey = match pick_journey(&timetable.journeys, date, end, max_transfers) {
+        Some(journey) => journey,
+        None => {
+            return Err(match max_transfers {
+                Some(max) => format!(
+                    "🚨 No journeys found on {} with at most {} transfers",
+                    format_date(&date),
+                    max
+                ),
+                None => format!("🚨 No journeys found on {}", format_date(&date)),
+            }
+            .into())
+        }
+    };
+    let warning = home_time.and_then(|home_time| build_home_warning(journey, date, home_time));
+
+    if output == "json" {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "from": trip.from,
+                "to": trip.to,
+                "date": format_date(&date),
+                "journey": journey_json(journey, date),
+                "warning": warning,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} journey from '{}' to '{}' on {}",
+        match end {
+            DayEnd::First => "🌅 First",
+            DayEnd::Last => "🌙 Last",
+        },
+        journey.departure(),
+        journey.legs[0].origin,
+        journey.legs[journey.transfers()].destination,
+        format_date(&date)
+    );
+    let timetable = Timetable {
+        journeys: vec![journey.clone()],
+        transfers: journey.transfers(),
+        ..timetable
+    };
+    build_timetable_table(&timetable, true).printstd();
+    if let Some(warning) = warning {
+        println!("{}", warning);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    #[test]
+    fn test_pick_first_journey() {
+        let timetable = fixture_timetable();
+        let (journeys, date) = (&timetable.journeys, fixture_date());
+        let first = pick_journey(journeys, date, DayEnd::First, None).unwrap();
+        assert_eq!(first.departure(), "06:04");
+        let first = pick_journey(journeys, date, DayEnd::First, Some(0)).unwrap();
+        assert_eq!(first.departure(), "21:19");
+    }
+
+    #[test]
+    fn test_pick_last_journey() {
+        let timetable = fixture_timetable();
+        let (journeys, date) = (&timetable.journeys, fixture_date());
+        let last = pick_journey(journeys, date, DayEnd::Last, None).unwrap();
+        assert_eq!(last.departure(), "21:19");
+        assert!(pick_journey(&journeys[..1], date, DayEnd::Last, Some(0)).is_none());
+    }
+
+    #[test]
+    fn test_build_home_warning() {
+        let timetable = fixture_timetable();
+        let (last, date) = (&timetable.journeys[1], fixture_date());
+        let time = |time: &str| parse_time(time).unwrap();
+        assert_eq!(
+            build_home_warning(last, date, time("22:00")).unwrap(),
+            "⚠️ The last journey leaves at 21:19, before your going home time 22:00"
+        );
+        assert!(build_home_warning(last, date, time("21:19")).is_none());
+    }
+
+    #[test]
+    fn test_build_home_warning_after_midnight() {
+        let timetable = fixture_timetable();
+        let (last, date) = (&timetable.journeys[1], fixture_date());
+        let time = |time: &str| parse_time(time).unwrap();
+        // going home after midnight is later than the evening train
+        assert_eq!(
+            build_home_warning(last, date, time("00:30")).unwrap(),
+            "⚠️ The last journey leaves at 21:19, before your going home time 00:30"
+        );
+        assert!(build_home_warning(last, date, time("05:00")).is_none());
+    }
+}
@@ -0,0 +1,239 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use crate::{
+    config::cli::parse_minutes,
+    rodalies::{
+        line::Line,
+        timetable::{Journey, Timetable},
+    },
+};
+
+/// The journey filters given by the CLI args.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JourneyFilter {
+    /// The maximum number of transfers, `0` for direct trains only.
+    pub max_transfers: Option<usize>,
+    /// The minimum minutes to change trains at every transfer.
+    pub min_transfer: Option<i64>,
+    /// The maximum minutes to wait at any transfer.
+    pub max_wait: Option<i64>,
+    /// The lines to take on some leg, empty for any.
+    pub lines: Vec<Line>,
+    /// The lines not to take.
+    pub exclude_lines: Vec<Line>,
+}
+
+impl JourneyFilter {
+    /// Tells whether no filter is set.
+    pub fn is_empty(&self) -> bool {
+        self == &JourneyFilter::default()
+    }
+
+    /// Tells whether the journey passes all the filters.
+    pub fn matches(&self, journey: &Journey) -> bool {
+        if self
+            .max_transfers
+            .is_some_and(|max| journey.transfers() > max)
+        {
+            return false;
+        }
+
+        // a leg whose line is unknown never takes a given line
+        let takes = |lines: &[Line], leg_line: &Option<Line>| match leg_line {
+            Some(leg_line) => lines.iter().any(|line| leg_line.is(line)),
+            None => false,
+        };
+        let leg_lines: Vec<Option<Line>> = journey.legs.iter().map(|leg| leg.line()).collect();
+        if !self.lines.is_empty() && !leg_lines.iter().any(|line| takes(&self.lines, line)) {
+            return false;
+        }
+        if leg_lines
+            .iter()
+            .any(|line| takes(&self.exclude_lines, line))
+        {
+            return false;
+        }
+
+        let waits = journey.wait_minutes();
+        if self.min_transfer.is_some() || self.max_wait.is_some() {
+            // a wait that cannot be read is not trusted to be long or short enough
+            if waits.iter().any(|wait| wait.is_none()) {
+                return false;
+            }
+        }
+        let waits: Vec<i64> = waits.into_iter().flatten().collect();
+        if self
+            .min_transfer
+            .is_some_and(|min| waits.iter().any(|wait| *wait < min))
+        {
+            return false;
+        }
+        if self
+            .max_wait
+            .is_some_and(|max| waits.iter().any(|wait| *wait > max))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Given a container of CLI args, it processes the `direct`, `max-transfers`, `min-transfer`, `max-wait`, `line` and `exclude-line` arguments.
+pub fn parse_filter(args: &ArgMatches) -> Result<JourneyFilter, Box<dyn Error>> {
+    let minutes = |name: &str| -> Result<Option<i64>, Box<dyn Error>> {
+        match args.try_get_one::<String>(name).ok().flatten() {
+            Some(value) => Ok(Some(parse_minutes(value)?)),
+            None => Ok(None),
+        }
+    };
+    let lines = |name: &str| -> Result<Vec<Line>, Box<dyn Error>> {
+        match args.try_get_many::<String>(name) {
+            Ok(Some(values)) => values.map(|value| value.parse()).collect(),
+            _ => Ok(Vec::new()),
+        }
+    };
+    Ok(JourneyFilter {
+        max_transfers: if args.get_flag("direct") {
+            Some(0)
+        } else {
+            args.try_get_one::<u64>("max-transfers")
+                .ok()
+                .flatten()
+                .map(|max| *max as usize)
+        },
+        min_transfer: minutes("min-transfer")?,
+        max_wait: minutes("max-wait")?,
+        lines: lines("line")?,
+        exclude_lines: lines("exclude-line")?,
+    })
+}
+
+/// Keeps only the journeys passing the filters, returning how many were left out.
+///
+/// The timetable transfers are updated to the ones of the remaining journeys.
+pub fn filter_timetable(timetable: &mut Timetable, filter: &JourneyFilter) -> usize {
+    let before = timetable.journeys.len();
+    timetable.journeys.retain(|journey| filter.matches(journey));
+    timetable.transfers = timetable
+        .journeys
+        .iter()
+        .map(|journey| journey.transfers())
+        .max()
+        .unwrap_or_default();
+    before - timetable.journeys.len()
+}
+
+/// Applies the filters to the timetable, telling how many journeys were left out.
+pub fn apply_filter(
+    timetable: &mut Timetable,
+    filter: &JourneyFilter,
+) -> Result<(), Box<dyn Error>> {
+    if filter.is_empty() {
+        return Ok(());
+    }
+
+    let hidden = filter_timetable(timetable, filter);
+    if timetable.journeys.is_empty() {
+        return Err(
+            "🚨 No journeys left after applying the filters, please try less strict ones".into(),
+        );
+    }
+    if hidden > 0 {
+        println!("🧹 {} journeys left out by the filters", hidden);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::fixture_timetable;
+
+    #[test]
+    fn test_filter_timetable_by_transfers() {
+        let mut filtered = fixture_timetable();
+        let direct = JourneyFilter {
+            max_transfers: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(filter_timetable(&mut filtered, &direct), 1);
+        assert_eq!(filtered.journeys[0].departure(), "21:19");
+        assert_eq!(filtered.transfers, 0);
+    }
+
+    #[test]
+    fn test_filter_timetable_by_transfer_waits() {
+        let timetable = fixture_timetable();
+        // the 06:04 journey waits 5 minutes at Barcelona-Sants
+        let tight = JourneyFilter {
+            min_transfer: Some(6),
+            ..Default::default()
+        };
+        let mut filtered = timetable.clone();
+        assert_eq!(filter_timetable(&mut filtered, &tight), 1);
+
+        let short = JourneyFilter {
+            min_transfer: Some(5),
+            max_wait: Some(5),
+            ..Default::default()
+        };
+        let mut filtered = timetable;
+        assert_eq!(filter_timetable(&mut filtered, &short), 0);
+        assert_eq!(filtered.transfers, 1);
+    }
+
+    #[test]
+    fn test_filter_timetable_by_lines() {
+        let timetable = fixture_timetable();
+        let line = |name: &str| name.parse::<Line>().unwrap();
+        let mut filtered = timetable.clone();
+        let r11 = JourneyFilter {
+            lines: vec![line("R11")],
+            ..Default::default()
+        };
+        assert_eq!(filter_timetable(&mut filtered, &r11), 0);
+        // the morning journey changes from an R11 to an R2, so it takes the R2 too
+        let mut filtered = timetable;
+        let r2 = JourneyFilter {
+            lines: vec![line("R2")],
+            ..Default::default()
+        };
+        assert_eq!(filter_timetable(&mut filtered, &r2), 1);
+        assert_eq!(filtered.journeys[0].departure(), "06:04");
+    }
+
+    #[test]
+    fn test_filter_timetable_excluding_lines() {
+        let timetable = fixture_timetable();
+        let line = |name: &str| name.parse::<Line>().unwrap();
+        let mut filtered = timetable.clone();
+        let no_r2 = JourneyFilter {
+            exclude_lines: vec![line("R2")],
+            ..Default::default()
+        };
+        assert_eq!(filter_timetable(&mut filtered, &no_r2), 1);
+        let mut filtered = timetable;
+        let r2_nord = JourneyFilter {
+            exclude_lines: vec![line("R2 Nord")],
+            ..Default::default()
+        };
+        assert_eq!(filter_timetable(&mut filtered, &r2_nord), 0);
+    }
+
+    #[test]
+    fn test_apply_filter_fails_without_journeys_left() {
+        let timetable = fixture_timetable();
+        let direct = JourneyFilter {
+            max_transfers: Some(0),
+            ..Default::default()
+        };
+        // the only journey left would be the morning one, with a transfer
+        let mut filtered = Timetable {
+            journeys: vec![timetable.journeys[0].clone()],
+            ..timetable
+        };
+        assert!(apply_filter(&mut filtered, &direct).is_err());
+    }
+}
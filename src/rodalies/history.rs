@@ -0,0 +1,401 @@
+use chrono::{Days, Local, NaiveDate, NaiveTime};
+use clap::ArgMatches;
+use prettytable::{Cell, Row};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt};
+use surf::Client;
+
+use crate::{
+    config::{
+        cli::{format_date, init_results_table},
+        store,
+    },
+    rodalies::{
+        arrive::arrival_deadline,
+        filter::JourneyFilter,
+        line::Line,
+        risk::TIGHT_TRANSFER,
+        station::Station,
+        timetable::{parse_time, run_search},
+        via::Via,
+    },
+};
+
+/// The name of the data file keeping the search history.
+const HISTORY_FILE: &str = "history.json";
+
+/// The maximum number of searches kept in the history.
+const HISTORY_SIZE: usize = 20;
+
+/// How the date of a search was chosen, so that repeating it keeps the same meaning.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateMode {
+    Today,
+    Tomorrow,
+    /// A specific date, in `dd/mm/yyyy` format.
+    Date(String),
+}
+
+impl DateMode {
+    /// Returns the actual date to search for.
+    pub fn date(&self) -> Result<NaiveDate, Box<dyn Error>> {
+        let today = Local::now().date_naive();
+        match self {
+            DateMode::Today => Ok(today),
+            DateMode::Tomorrow => Ok(today + Days::new(1)),
+            DateMode::Date(date) => Ok(NaiveDate::parse_from_str(date, "%d/%m/%Y")?),
+        }
+    }
+}
+
+impl From<NaiveDate> for DateMode {
+    fn from(date: NaiveDate) -> Self {
+        DateMode::Date(format_date(&date))
+    }
+}
+
+impl fmt::Display for DateMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateMode::Today => write!(f, "today"),
+            DateMode::Tomorrow => write!(f, "tomorrow"),
+            DateMode::Date(date) => write!(f, "{}", date),
+        }
+    }
+}
+
+/// A completed timetable search.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The origin's station.
+    pub from: Station,
+    /// The destination's station.
+    pub to: Station,
+    /// The date of the search.
+    pub date: DateMode,
+    /// The hour (`HH`) from which trains were listed.
+    pub hour: String,
+    /// The filters the journeys were listed with.
+    #[serde(default)]
+    pub filter: JourneyFilter,
+    /// The field the journeys were sorted by, if any.
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// The minutes below which a transfer was taken as tight.
+    #[serde(default = "default_tight_transfer")]
+    pub tight_transfer: i64,
+    /// The stations stopped at on the way, if any.
+    #[serde(default)]
+    pub via: Vec<Via>,
+    /// The time (`HH:MM`) to arrive by, if any.
+    #[serde(default)]
+    pub arrive_by: Option<String>,
+    /// The minutes to arrive before the time to arrive by.
+    #[serde(default)]
+    pub buffer: i64,
+    /// The other origins' station IDs or names, ranked by arrival together with the first one.
+    #[serde(default)]
+    pub more_from: Vec<String>,
+    /// The other destinations' station IDs or names, ranked by arrival together with the first one.
+    #[serde(default)]
+    pub more_to: Vec<String>,
+}
+
+/// The tight transfer minutes of the searches saved before they were kept.
+fn default_tight_transfer() -> i64 {
+    TIGHT_TRANSFER
+}
+
+impl HistoryEntry {
+    /// A plain search of the trip on the date from the hour, without any other option.
+    pub fn new(from: Station, to: Station, date: DateMode, hour: String) -> Self {
+        HistoryEntry {
+            from,
+            to,
+            date,
+            hour,
+            filter: JourneyFilter::default(),
+            sort: None,
+            tight_transfer: TIGHT_TRANSFER,
+            via: Vec::new(),
+            arrive_by: None,
+            buffer: 0,
+            more_from: Vec::new(),
+            more_to: Vec::new(),
+        }
+    }
+
+    /// Tells whether the search ranks the journeys of several origins or destinations.
+    pub fn is_merged(&self) -> bool {
+        !self.more_from.is_empty() || !self.more_to.is_empty()
+    }
+
+    /// Returns the latest arrival time of a search arriving by a time.
+    pub fn deadline(&self) -> Result<Option<NaiveTime>, Box<dyn Error>> {
+        let arrive_by = match &self.arrive_by {
+            Some(arrive_by) => arrive_by,
+            None => return Ok(None),
+        };
+        match parse_time(arrive_by) {
+            Some(arrive_by) => Ok(Some(arrival_deadline(arrive_by, self.buffer)?)),
+            None => Err("🚨 Please, specify the arrival time as HH:MM".into()),
+        }
+    }
+
+    /// Formats the options of the search as their CLI args, like `--via Figueres=10m --direct --sort duration`.
+    pub fn options(&self) -> String {
+        // values with spaces are quoted, so the args can be copied as they are
+        let quote = |value: &str| match value.contains(' ') {
+            true => format!("'{}'", value),
+            false => value.to_string(),
+        };
+        let filter = &self.filter;
+        let mut options: Vec<String> = Vec::new();
+        options.extend(
+            self.more_from
+                .iter()
+                .map(|from| format!("--from {}", quote(from))),
+        );
+        options.extend(self.more_to.iter().map(|to| format!("--to {}", quote(to))));
+        options.extend(
+            self.via
+                .iter()
+                .map(|via| format!("--via {}", quote(&format!("{}={}m", via.station, via.stop)))),
+        );
+        if let Some(arrive_by) = &self.arrive_by {
+            options.push(format!("--arrive-by {}", arrive_by));
+            if self.buffer > 0 {
+                options.push(format!("--buffer {}m", self.buffer));
+            }
+        }
+        match filter.max_transfers {
+            Some(0) => options.push("--direct".to_string()),
+            Some(max) => options.push(format!("--max-transfers {}", max)),
+            None => {}
+        }
+        if let Some(min) = filter.min_transfer {
+            options.push(format!("--min-transfer {}m", min));
+        }
+        if let Some(max) = filter.max_wait {
+            options.push(format!("--max-wait {}m", max));
+        }
+        // branches are given without space, as the other values
+        let line = |line: &Line| line.to_string().replace(' ', "");
+        options.extend(filter.lines.iter().map(|l| format!("--line {}", line(l))));
+        options.extend(
+            filter
+                .exclude_lines
+                .iter()
+                .map(|l| format!("--exclude-line {}", line(l))),
+        );
+        if let Some(sort) = &self.sort {
+            options.push(format!("--sort {}", sort));
+        }
+        if self.tight_transfer != TIGHT_TRANSFER {
+            options.push(format!("--tight-transfer {}m", self.tight_transfer));
+        }
+        options.join(" ")
+    }
+}
+
+/// Loads the search history, most recent searches first.
+pub fn load_history() -> Result<Vec<HistoryEntry>, Box<dyn Error>> {
+    store::load(HISTORY_FILE)
+}
+
+/// Adds the entry on top of the history, removing any previous identical search.
+fn add_entry(history: &mut Vec<HistoryEntry>, entry: HistoryEntry) {
+    history.retain(|previous| previous != &entry);
+    history.insert(0, entry);
+    history.truncate(HISTORY_SIZE);
+}
+
+/// Saves the completed search on the history. Failing to do so does not fail the search.
+pub fn save_to_history(entry: HistoryEntry) {
+    let result = load_history().and_then(|mut history| {
+        add_entry(&mut history, entry);
+        store::save(HISTORY_FILE, &history)
+    });
+    if let Err(error) = result {
+        eprintln!("🕵️ Could not save the search on the history: {}", error);
+    }
+}
+
+/// Displays a table with the given history entries, numbered from 1.
+pub fn display_history(history: &[HistoryEntry]) {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Option"),
+        Cell::new("Origin"),
+        Cell::new("Destination"),
+        Cell::new("Date"),
+        Cell::new("Hour"),
+        Cell::new("Options"),
+    ]));
+
+    for (index, entry) in history.iter().enumerate() {
+        results_table.add_row(Row::new(vec![
+            Cell::new((index + 1).to_string().as_str()).style_spec("r"),
+            Cell::new(&entry.from.name),
+            Cell::new(&entry.to.name),
+            Cell::new(entry.date.to_string().as_str()),
+            Cell::new(&entry.hour),
+            Cell::new(&entry.options()),
+        ]));
+    }
+    results_table.printstd();
+}
+
+/// Displays the recent searches.
+pub fn search_history() -> Result<(), Box<dyn Error>> {
+    let history = load_history()?;
+    if history.is_empty() {
+        return Err("🚨 There are no searches on the history yet".into());
+    }
+
+    println!("🕘 Listing your recent searches, repeat any of them with '--again <option>'");
+    display_history(&history);
+    Ok(())
+}
+
+/// Repeats the search at the position given by the `again` argument (1 being the last one).
+pub async fn search_again(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let option = *args.get_one::<usize>("again").unwrap_or(&1);
+    let history = load_history()?;
+    let entry = match option.checked_sub(1).and_then(|index| history.get(index)) {
+        Some(entry) => entry.clone(),
+        None => {
+            return Err(format!(
+            "🚨 There is no search number {} on the history, see them with the 'history' command",
+            option
+        )
+            .into())
+        }
+    };
+
+    let date = format_date(&entry.date.date()?);
+    println!(
+        "🔁 Repeating search from '{}' to '{}' for date {} from {}h",
+        entry.from.name, entry.to.name, date, entry.hour
+    );
+    run_search(client, entry).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(from: &str, to: &str, date: DateMode) -> HistoryEntry {
+        HistoryEntry::new(
+            Station {
+                id: from.to_string(),
+                name: from.to_string(),
+            },
+            Station {
+                id: to.to_string(),
+                name: to.to_string(),
+            },
+            date,
+            "00".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_add_entry_moves_repeated_searches_on_top() {
+        let mut history = vec![
+            entry("1", "2", DateMode::Today),
+            entry("3", "4", DateMode::Tomorrow),
+        ];
+        add_entry(&mut history, entry("3", "4", DateMode::Tomorrow));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0], entry("3", "4", DateMode::Tomorrow));
+
+        for i in 0..HISTORY_SIZE {
+            add_entry(&mut history, entry(&i.to_string(), "9", DateMode::Today));
+        }
+        assert_eq!(history.len(), HISTORY_SIZE);
+    }
+
+    #[test]
+    fn test_date_mode_serialization_and_date() {
+        let date = DateMode::Date("07/03/2024".to_string());
+        assert_eq!(
+            serde_json::to_string(&date).unwrap(),
+            r#"{"date":"07/03/2024"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<DateMode>(r#""tomorrow""#).unwrap(),
+            DateMode::Tomorrow
+        );
+        assert_eq!(
+            date.date().unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_entry_options_of_filters() {
+        let mut filtered = entry("1", "2", DateMode::Today);
+        assert_eq!(filtered.options(), "");
+
+        filtered.filter = JourneyFilter {
+            max_transfers: Some(0),
+            max_wait: Some(20),
+            lines: vec!["R2 Nord".parse().unwrap()],
+            ..Default::default()
+        };
+        filtered.sort = Some("duration".to_string());
+        filtered.tight_transfer = 8;
+        assert_eq!(
+            filtered.options(),
+            "--direct --max-wait 20m --line R2Nord --sort duration --tight-transfer 8m"
+        );
+        let json = serde_json::to_string(&filtered).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HistoryEntry>(&json).unwrap(),
+            filtered
+        );
+    }
+
+    #[test]
+    fn test_entry_options_of_via_arrive_by_and_merged_searches() {
+        let mut via = entry("1", "2", DateMode::Today);
+        assert_eq!(via.options(), "");
+        via.via = vec![Via {
+            station: "Sant Vicenç".to_string(),
+            stop: 10,
+        }];
+        assert_eq!(via.options(), "--via 'Sant Vicenç=10m'");
+        assert!(!via.is_merged());
+        let json = serde_json::to_string(&via).unwrap();
+        assert_eq!(serde_json::from_str::<HistoryEntry>(&json).unwrap(), via);
+
+        let mut arrive_by = entry("1", "2", DateMode::Today);
+        arrive_by.arrive_by = Some("09:30".to_string());
+        arrive_by.buffer = 10;
+        assert_eq!(arrive_by.options(), "--arrive-by 09:30 --buffer 10m");
+        assert_eq!(
+            arrive_by.deadline().unwrap(),
+            NaiveTime::from_hms_opt(9, 20, 0)
+        );
+        assert!(entry("1", "2", DateMode::Today)
+            .deadline()
+            .unwrap()
+            .is_none());
+
+        let mut merged = entry("1", "2", DateMode::Today);
+        merged.more_from = vec!["3".to_string()];
+        assert_eq!(merged.options(), "--from 3");
+        assert!(merged.is_merged());
+    }
+
+    #[test]
+    fn test_older_entries_are_plain_searches() {
+        let older = r#"{"from":{"id":"1","name":"1"},"to":{"id":"2","name":"2"},"date":"today","hour":"00"}"#;
+        assert_eq!(
+            serde_json::from_str::<HistoryEntry>(older).unwrap(),
+            entry("1", "2", DateMode::Today)
+        );
+    }
+}
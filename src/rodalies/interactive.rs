@@ -1,112 +1,453 @@
+use chrono::{Days, NaiveDate};
 use clap::ArgMatches;
 use std::error::Error;
 use std::io;
 use surf::Client;
 
 use crate::{
-    config::cli::parse_date,
+    config::cli::{format_date, has_date, parse_date},
     rodalies::{
-        station::{get_stations_list, search_station_input, Station},
-        timetable::search_timetable_input,
+        history::{display_history, load_history, save_to_history, DateMode, HistoryEntry},
+        station::{get_stations_list, is_station_id, search_station_input, Station},
+        timetable::{export_timetable, run_search, search_timetable_input, Timetable},
     },
 };
 
-#[allow(unused_assignments)]
+const STATION_NAME_HINT: &str = "Please, provide at least 3 characters of the station name.";
+const OPTION_HINT: &str = "Please, provide a valid input number.";
+const DATE_HINT: &str = "Please, provide a valid date option.";
+const HOUR_HINT: &str = "Please, provide a valid hour between 0 and 23.";
+const ACTION_HINT: &str = "Please, provide a valid action option.";
+const EXPORT_HINT: &str = "Please, provide a valid file name.";
+
+/// The maximum number of recent searches offered before asking for the stations.
+const RECENT_SIZE: usize = 9;
+
+/// The answer given by the user on any of the interactive prompts.
+enum Answer {
+    /// The user wants to leave the interactive search (`q`).
+    Quit,
+    /// The user wants to go back to the previous step (`b`).
+    Back,
+    /// Any other (trimmed) input.
+    Text(String),
+}
+
+/// The outcome of an interactive step.
+enum Selection<T> {
+    Quit,
+    Back,
+    Selected(T),
+}
+
+/// The follow-up actions offered once the timetable is shown.
+enum Action {
+    Swap,
+    NextDay,
+    PreviousDay,
+    ChangeHour,
+    Export,
+    NewSearch,
+    Quit,
+}
+
+/// The steps of the interactive search, in the order they are asked.
+enum Step {
+    Recent,
+    Origin,
+    Destination,
+    Date,
+    Hour,
+}
+
+/// Runs the whole timetable search interactively, asking for each of the search values.
+///
+/// Invalid answers are asked again, `b` goes back to the previous step and `q` quits.
+/// Once the timetable is shown, follow-up actions are offered on the same session.
 pub async fn search_interactive(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
-    let mut from = String::new();
-    let mut to = String::new();
-    let mut date = String::new();
-    let mut input = String::new();
+    let mut from: Option<Station> = None;
+    let mut to: Option<Station> = None;
+    let mut date_mode = DateMode::Today;
     let stations_list = get_stations_list(client).await?;
 
-    println!("🎬 Which is your origin's station name?");
-    let origin_station_list: Vec<Station> = match io::stdin().read_line(&mut input) {
-        Ok(n) => {
-            if n >= 4 {
-                search_station_input(&stations_list, input.trim().to_string()).await?
-            } else {
-                return Err("Please, provide at least 3 characters of the station name.".into());
+    // date flags, if provided, take precedence over asking for the date
+    let date_from_flags = has_date(args);
+
+    'search: loop {
+        let mut step = Step::Recent;
+        let mut hour = loop {
+            step = match step {
+                Step::Recent => match search_recent_input()? {
+                    Selection::Quit => return Ok(()),
+                    Selection::Back => Step::Origin,
+                    // the follow-up actions only know about plain searches, so the others are just repeated
+                    Selection::Selected(entry) if !entry.options().is_empty() => {
+                        if let Err(error) = run_search(client, entry).await {
+                            println!("{}", error);
+                        }
+                        continue 'search;
+                    }
+                    Selection::Selected(entry) => {
+                        from = Some(entry.from);
+                        to = Some(entry.to);
+                        date_mode = entry.date;
+                        break entry.hour;
+                    }
+                },
+                Step::Origin => match search_station_step(&stations_list, "origin").await? {
+                    Selection::Quit => return Ok(()),
+                    Selection::Back => Step::Recent,
+                    Selection::Selected(station) => {
+                        from = Some(station);
+                        Step::Destination
+                    }
+                },
+                Step::Destination => {
+                    match search_station_step(&stations_list, "destination").await? {
+                        Selection::Quit => return Ok(()),
+                        Selection::Back => Step::Origin,
+                        Selection::Selected(station) => {
+                            to = Some(station);
+                            Step::Date
+                        }
+                    }
+                }
+                Step::Date if date_from_flags => {
+                    date_mode = DateMode::Date(parse_date(args)?);
+                    Step::Hour
+                }
+                Step::Date => match search_date_input()? {
+                    Selection::Quit => return Ok(()),
+                    Selection::Back => Step::Destination,
+                    Selection::Selected(selected) => {
+                        date_mode = selected;
+                        Step::Hour
+                    }
+                },
+                Step::Hour => match search_hour_input()? {
+                    Selection::Quit => return Ok(()),
+                    Selection::Back if date_from_flags => Step::Destination,
+                    Selection::Back => Step::Date,
+                    Selection::Selected(hour) => break hour,
+                },
+            };
+        };
+
+        let (Some(mut origin), Some(mut destination)) = (from.clone(), to.clone()) else {
+            return Err("🚨 Please, specify origin and destination stations".into());
+        };
+
+        let mut timetable: Option<Timetable> = None;
+        let mut refresh = true;
+        loop {
+            let date = date_mode.date()?;
+            if refresh {
+                println!(
+                    "🔍 Searching timetable from '{}' to '{}' for date {} from {}h",
+                    origin.name,
+                    destination.name,
+                    format_date(&date),
+                    hour
+                );
+                timetable = match search_timetable_input(
+                    client,
+                    origin.id.clone(),
+                    destination.id.clone(),
+                    format_date(&date),
+                    hour.clone(),
+                )
+                .await
+                {
+                    Ok(timetable) => {
+                        save_to_history(HistoryEntry::new(
+                            origin.clone(),
+                            destination.clone(),
+                            date_mode.clone(),
+                            hour.clone(),
+                        ));
+                        Some(timetable)
+                    }
+                    Err(error) => {
+                        println!("{}", error);
+                        None
+                    }
+                };
+            }
+            refresh = true;
+
+            match search_action_input()? {
+                Action::Swap => std::mem::swap(&mut origin, &mut destination),
+                Action::NextDay => date_mode = DateMode::from(date + Days::new(1)),
+                Action::PreviousDay => date_mode = DateMode::from(date - Days::new(1)),
+                Action::ChangeHour => match search_hour_input()? {
+                    Selection::Quit => return Ok(()),
+                    Selection::Back => refresh = false,
+                    Selection::Selected(selected) => hour = selected,
+                },
+                Action::Export => {
+                    refresh = false;
+                    match &timetable {
+                        Some(timetable) => {
+                            let file_name = format!(
+                                "rodalies-{}-{}-{}.csv",
+                                origin.id,
+                                destination.id,
+                                date.format("%Y%m%d")
+                            );
+                            if let Selection::Quit = search_export_input(timetable, file_name)? {
+                                return Ok(());
+                            }
+                        }
+                        None => println!("🚨 There is no timetable to export"),
+                    }
+                }
+                Action::NewSearch => break,
+                Action::Quit => return Ok(()),
             }
         }
-        Err(error) => return Err(format!("error: {}", error).into()),
+    }
+}
+
+/// Reads a line from the standard input. Reaching the end of the input fails with the given `hint`.
+fn read_answer(hint: &str) -> Result<Answer, Box<dyn Error>> {
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(hint.into());
+    }
+
+    Ok(match input.trim() {
+        "q" => Answer::Quit,
+        "b" => Answer::Back,
+        trimmed => Answer::Text(trimmed.to_string()),
+    })
+}
+
+/// Offers the recent searches, if any, to be repeated. Returns `Back` when the user wants a new search.
+fn search_recent_input() -> Result<Selection<HistoryEntry>, Box<dyn Error>> {
+    let mut history = match load_history() {
+        Ok(history) => history,
+        Err(error) => {
+            println!("🕵️ Could not load the search history: {}", error);
+            return Ok(Selection::Back);
+        }
     };
+    if history.is_empty() {
+        return Ok(Selection::Back);
+    }
+    history.truncate(RECENT_SIZE);
+
+    println!("🕘 Your recent searches:");
+    display_history(&history);
 
-    input.clear();
-    if origin_station_list.len() == 1 {
+    loop {
         println!(
-            "🎬 Perfect match! Using '{}' as the selected origin's station",
-            origin_station_list[0].name
+            "🎬 Which recent search do you want to repeat? (1-{}, empty for a new search, 'q' to quit)",
+            history.len()
         );
-        from = origin_station_list[0].clone().id;
-    } else {
-        println!("🎬 Which option does match with your origin's station name? ");
-        from = match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let trimmed = input.trim();
-                let index = match trimmed.parse::<usize>() {
-                    Ok(index) => {
-                        if index <= origin_station_list.len() && index >= 1 {
-                            index - 1
-                        } else {
-                            return Err("Please, provide a valid input number.".into());
-                        }
-                    }
-                    Err(..) => return Err(format!("this was not an integer: {}", trimmed).into()),
-                };
-                println!("you have selected {}", origin_station_list[index].name);
-                origin_station_list[index].clone().id
-            }
-            Err(error) => return Err(format!("error: {}", error).into()),
-        };
+        match read_answer(OPTION_HINT)? {
+            Answer::Quit => return Ok(Selection::Quit),
+            Answer::Back => return Ok(Selection::Back),
+            Answer::Text(input) if input.is_empty() => return Ok(Selection::Back),
+            Answer::Text(input) => match input.parse::<usize>() {
+                Ok(index) if index >= 1 && index <= history.len() => {
+                    return Ok(Selection::Selected(history.swap_remove(index - 1)))
+                }
+                _ => println!("🚨 {}", OPTION_HINT),
+            },
+        }
     }
+}
+
+/// Asks for a station, either by its ID or by a name pattern to choose from, until one is selected.
+async fn search_station_step(
+    stations_list: &[Station],
+    kind: &str,
+) -> Result<Selection<Station>, Box<dyn Error>> {
+    loop {
+        println!(
+            "🎬 Which is your {}'s station name or ID? ('b' to go back, 'q' to quit)",
+            kind
+        );
+        let search = match read_answer(STATION_NAME_HINT)? {
+            Answer::Quit => return Ok(Selection::Quit),
+            Answer::Back => return Ok(Selection::Back),
+            Answer::Text(search) => search,
+        };
 
-    input.clear();
-    println!("🎬 Which is your destination's station name?");
-    let destination_station_list: Vec<Station> = match io::stdin().read_line(&mut input) {
-        Ok(n) => {
-            if n >= 4 {
-                search_station_input(&stations_list, input.trim().to_string()).await?
-            } else {
-                return Err("Please, provide at least 3 characters of the station name.".into());
+        if is_station_id(&search) {
+            match stations_list.iter().find(|station| station.id == search) {
+                Some(station) => {
+                    println!(
+                        "🎬 Using '{}' as the selected {}'s station",
+                        station.name, kind
+                    );
+                    return Ok(Selection::Selected(station.clone()));
+                }
+                None => {
+                    println!("🚨 No station found with ID '{}'", search);
+                    continue;
+                }
             }
         }
-        Err(error) => return Err(format!("error: {}", error).into()),
-    };
 
-    input.clear();
-    if destination_station_list.len() == 1 {
+        if search.chars().count() < 3 {
+            println!("🚨 {}", STATION_NAME_HINT);
+            continue;
+        }
+
+        let found_station_list = match search_station_input(stations_list, search).await {
+            Ok(found_station_list) => found_station_list,
+            Err(error) => {
+                println!("{}", error);
+                continue;
+            }
+        };
+
+        if found_station_list.len() == 1 {
+            println!(
+                "🎬 Perfect match! Using '{}' as the selected {}'s station",
+                found_station_list[0].name, kind
+            );
+            return Ok(Selection::Selected(found_station_list[0].clone()));
+        }
+
+        if let Some(selection) = search_option_input(&found_station_list, kind)? {
+            return Ok(selection);
+        }
+    }
+}
+
+/// Asks which of the found stations is the desired one. Returns `None` when the user wants to search again.
+fn search_option_input(
+    found_station_list: &[Station],
+    kind: &str,
+) -> Result<Option<Selection<Station>>, Box<dyn Error>> {
+    loop {
         println!(
-            "🎬 Perfect match! Using '{}' as the selected destination's station",
-            destination_station_list[0].name
+            "🎬 Which option does match with your {}'s station name? ('b' to search again, 'q' to quit)",
+            kind
         );
-        to = destination_station_list[0].clone().id;
-    } else {
-        println!("🎬 Which option does match with your destination's station name? ");
-        input.clear();
-        to = match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let trimmed = input.trim();
-                let index = match trimmed.parse::<usize>() {
-                    Ok(index) => {
-                        if index <= destination_station_list.len() && index >= 1 {
-                            index - 1
-                        } else {
-                            return Err("Please, provide a valid input number.".into());
-                        }
-                    }
-                    Err(..) => return Err(format!("this was not an integer: {}", trimmed).into()),
-                };
-                println!("you have selected {}", destination_station_list[index].name);
-                destination_station_list[index].clone().id
+        let trimmed = match read_answer(OPTION_HINT)? {
+            Answer::Quit => return Ok(Some(Selection::Quit)),
+            Answer::Back => return Ok(None),
+            Answer::Text(trimmed) => trimmed,
+        };
+
+        match trimmed.parse::<usize>() {
+            Ok(index) if index >= 1 && index <= found_station_list.len() => {
+                println!("you have selected {}", found_station_list[index - 1].name);
+                return Ok(Some(Selection::Selected(
+                    found_station_list[index - 1].clone(),
+                )));
             }
-            Err(error) => return Err(format!("error: {}", error).into()),
+            Ok(..) => println!("🚨 {}", OPTION_HINT),
+            Err(..) => println!("🚨 this was not an integer: {}", trimmed),
+        }
+    }
+}
+
+/// Asks for the date of the trip: today (default), tomorrow or a specific `dd/mm/yyyy` date.
+fn search_date_input() -> Result<Selection<DateMode>, Box<dyn Error>> {
+    loop {
+        println!("📅 Which day do you want to travel? 1) today (default), 2) tomorrow, 3) another date ('b' to go back, 'q' to quit)");
+        let date = match read_answer(DATE_HINT)? {
+            Answer::Quit => return Ok(Selection::Quit),
+            Answer::Back => return Ok(Selection::Back),
+            Answer::Text(option) => match option.as_str() {
+                "" | "1" => DateMode::Today,
+                "2" => DateMode::Tomorrow,
+                "3" => match search_specific_date_input()? {
+                    Selection::Quit => return Ok(Selection::Quit),
+                    Selection::Back => continue,
+                    Selection::Selected(date) => DateMode::from(date),
+                },
+                _ => {
+                    println!("🚨 {}", DATE_HINT);
+                    continue;
+                }
+            },
         };
+
+        return Ok(Selection::Selected(date));
     }
+}
 
-    // TODO maybe make this interactive too - options: 1) today, 2) tomorrow, 3) input day, month, year (provide default first)
-    date = parse_date(args)?;
+/// Asks for a specific `dd/mm/yyyy` date.
+fn search_specific_date_input() -> Result<Selection<NaiveDate>, Box<dyn Error>> {
+    loop {
+        println!("📅 Which date? (dd/mm/yyyy)");
+        match read_answer(DATE_HINT)? {
+            Answer::Quit => return Ok(Selection::Quit),
+            Answer::Back => return Ok(Selection::Back),
+            Answer::Text(input) => match NaiveDate::parse_from_str(&input, "%d/%m/%Y") {
+                Ok(date) => return Ok(Selection::Selected(date)),
+                Err(..) => println!("🚨 this was not a valid date: {}", input),
+            },
+        }
+    }
+}
 
-    search_timetable_input(client, from, to, date).await?;
+/// Asks for the hour from which to list trains, defaulting to the start of the day.
+fn search_hour_input() -> Result<Selection<String>, Box<dyn Error>> {
+    loop {
+        println!("🕐 From which hour do you want to list trains? (0-23, default = 0, 'b' to go back, 'q' to quit)");
+        match read_answer(HOUR_HINT)? {
+            Answer::Quit => return Ok(Selection::Quit),
+            Answer::Back => return Ok(Selection::Back),
+            Answer::Text(input) if input.is_empty() => {
+                return Ok(Selection::Selected("00".to_string()))
+            }
+            Answer::Text(input) => match input.parse::<u32>() {
+                Ok(hour) if hour < 24 => return Ok(Selection::Selected(format!("{:02}", hour))),
+                _ => println!("🚨 {}", HOUR_HINT),
+            },
+        }
+    }
+}
 
-    Ok(())
+/// Asks what to do once the timetable is shown.
+fn search_action_input() -> Result<Action, Box<dyn Error>> {
+    loop {
+        println!("🎬 What next? 1) swap origin and destination, 2) next day, 3) previous day, 4) change start hour, 5) export timetable to CSV, 6) new search ('q' to quit)");
+        match read_answer(ACTION_HINT)? {
+            Answer::Quit => return Ok(Action::Quit),
+            Answer::Back => println!("🚨 {}", ACTION_HINT),
+            Answer::Text(option) => match option.as_str() {
+                "1" => return Ok(Action::Swap),
+                "2" => return Ok(Action::NextDay),
+                "3" => return Ok(Action::PreviousDay),
+                "4" => return Ok(Action::ChangeHour),
+                "5" => return Ok(Action::Export),
+                "6" => return Ok(Action::NewSearch),
+                _ => println!("🚨 {}", ACTION_HINT),
+            },
+        }
+    }
+}
+
+/// Asks for the CSV file name to export the timetable to, defaulting to `file_name`.
+fn search_export_input(
+    timetable: &Timetable,
+    file_name: String,
+) -> Result<Selection<String>, Box<dyn Error>> {
+    loop {
+        println!(
+            "💾 Which file do you want to export the timetable to? (default = {}, 'b' to go back, 'q' to quit)",
+            file_name
+        );
+        let path = match read_answer(EXPORT_HINT)? {
+            Answer::Quit => return Ok(Selection::Quit),
+            Answer::Back => return Ok(Selection::Back),
+            Answer::Text(input) if input.is_empty() => file_name.clone(),
+            Answer::Text(input) => input,
+        };
+
+        match export_timetable(timetable, &path) {
+            Ok(()) => {
+                println!("💾 Timetable exported to '{}'", path);
+                return Ok(Selection::Selected(path));
+            }
+            Err(error) => println!("🚨 Could not export the timetable to '{}': {}", path, error),
+        }
+    }
 }
@@ -0,0 +1,153 @@
+use prettytable::{
+    color::{self, Color},
+    Attr, Cell,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, str::FromStr};
+
+/// A train line, such as `R1`, `R2 Nord`, `R11`, `RG1` or `RT2`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Line {
+    /// The line family prefix: `R` for rodalies, `RG`, `RL` and `RT` for the regional ones.
+    pub family: String,
+    /// The line number within its family.
+    pub number: u32,
+    /// The branch of the line, `Nord` or `Sud`, if any.
+    pub branch: Option<String>,
+}
+
+impl Line {
+    /// Tells whether the line is the given one, any of its branches if the given one has none.
+    pub fn is(&self, other: &Line) -> bool {
+        self.family == other.family
+            && self.number == other.number
+            && (other.branch.is_none() || self.branch == other.branch)
+    }
+
+    /// The service the line belongs to: `R` for the commuter lines, `regional` for the R1x ones, or its `RG`, `RL` or `RT` family.
+    pub fn service(&self) -> &str {
+        match (self.family.as_str(), self.number) {
+            ("R", number) if number >= 10 => "regional",
+            (family, _) => family,
+        }
+    }
+
+    /// The line colour, as the closest one of the terminal colours to the official line colour.
+    pub fn colour(&self) -> Option<Color> {
+        let colour = match (self.family.as_str(), self.number, &self.branch) {
+            ("R", 1, _) => color::BRIGHT_BLUE,
+            ("R", 2, None) => color::GREEN,
+            ("R", 2, Some(_)) => color::BRIGHT_GREEN,
+            ("R", 3, _) | ("R", 16, _) => color::RED,
+            ("R", 4, _) | ("R", 15, _) | ("RL", 3, _) => color::YELLOW,
+            ("R", 7, _) | ("R", 14, _) => color::MAGENTA,
+            ("R", 8, _) | ("R", 13, _) | ("RT", 2, _) => color::BRIGHT_MAGENTA,
+            ("R", 11, _) => color::BLUE,
+            ("R", 12, _) | ("RL", 4, _) => color::BRIGHT_YELLOW,
+            ("R", 17, _) => color::BRIGHT_RED,
+            ("RG", 1, _) => color::BRIGHT_CYAN,
+            ("RT", 1, _) => color::CYAN,
+            _ => return None,
+        };
+        Some(colour)
+    }
+}
+
+impl FromStr for Line {
+    type Err = Box<dyn Error>;
+
+    /// Parses a line name as shown by the rodalies site (e.g. `R2 Nord`) or in short (e.g. `r2n`).
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let unknown = || -> Box<dyn Error> {
+            format!(
+                "🚨 Unknown train line '{}', please use names like R2, R2 Nord, R11 or RG1",
+                name
+            )
+            .into()
+        };
+        let name: String = name.split_whitespace().collect::<String>().to_uppercase();
+
+        let digits = name
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(unknown)?;
+        let (family, rest) = name.split_at(digits);
+        if !matches!(family, "R" | "RG" | "RL" | "RT") {
+            return Err(unknown());
+        }
+        let letters = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (number, branch) = rest.split_at(letters);
+        let branch = match branch {
+            "" => None,
+            "N" | "NORD" => Some("Nord".to_string()),
+            "S" | "SUD" => Some("Sud".to_string()),
+            _ => return Err(unknown()),
+        };
+
+        Ok(Line {
+            family: family.to_string(),
+            number: number.parse().map_err(|_| unknown())?,
+            branch,
+        })
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.family, self.number)?;
+        match &self.branch {
+            Some(branch) => write!(f, " {}", branch),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builds the table cell of a train, coloured with its line colour when known.
+pub fn line_cell(train: &str) -> Cell {
+    let cell = Cell::new(train);
+    match train.parse::<Line>().ok().and_then(|line| line.colour()) {
+        Some(colour) => cell
+            .with_style(Attr::Bold)
+            .with_style(Attr::ForegroundColor(colour)),
+        None => cell,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let line = |name: &str| name.parse::<Line>().unwrap();
+
+        assert_eq!(
+            line("R2 Nord"),
+            Line {
+                family: "R".to_string(),
+                number: 2,
+                branch: Some("Nord".to_string()),
+            }
+        );
+        assert_eq!(line("r2n"), line("R2 Nord"));
+        assert_eq!(line("R11").to_string(), "R11");
+        assert_eq!(line("RG1").family, "RG");
+        assert_eq!(line("rt 2").to_string(), "RT2");
+        assert!("S1".parse::<Line>().is_err());
+        assert!("R".parse::<Line>().is_err());
+        assert!("R2 Est".parse::<Line>().is_err());
+
+        assert!(line("R2 Sud").is(&line("R2")));
+        assert!(!line("R2").is(&line("R2 Sud")));
+        assert!(!line("R1").is(&line("R11")));
+
+        assert_eq!(line("R11").service(), "regional");
+        assert_eq!(line("R2 Sud").service(), "R");
+        assert_eq!(line("RT2").service(), "RT");
+
+        assert_eq!(line("R11").colour(), Some(color::BLUE));
+        assert_eq!(line("R2 Nord").colour(), Some(color::BRIGHT_GREEN));
+        assert_eq!(line("R99").colour(), None);
+    }
+}
@@ -0,0 +1,306 @@
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use clap::ArgMatches;
+use prettytable::{Cell, Row, Table};
+use std::{error::Error, io::stdout, time::Duration};
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, init_results_table, parse_query_date},
+    rodalies::{
+        station::{get_stations_list, resolve_station, Station},
+        timetable::{get_timetables, parse_time, Journey, Timetable, TimetableError},
+        trip::Trip,
+    },
+};
+
+/// The fastest journey found between two stations.
+pub struct MatrixEntry {
+    /// The trip between the two stations.
+    pub trip: Trip,
+    /// The fastest journey departing within the window, if any, or why it could not be requested.
+    pub fastest: Result<Option<Journey>, String>,
+}
+
+/// Given a container of CLI args, it processes the `after` and `before` arguments of the departure window.
+pub fn parse_window(args: &ArgMatches) -> Result<(NaiveTime, NaiveTime), Box<dyn Error>> {
+    let time = |name: &str, default: NaiveTime| match args.get_one::<String>(name) {
+        Some(time) => parse_time(time)
+            .ok_or_else(|| format!("🚨 Please, specify the '{}' time as HH:MM", name)),
+        None => Ok(default),
+    };
+    let after = time("after", NaiveTime::MIN)?;
+    let before = time(
+        "before",
+        NaiveTime::from_hms_opt(23, 59, 0).unwrap_or(NaiveTime::MIN),
+    )?;
+
+    if before < after {
+        return Err("🚨 Please, specify a departure window ending after it starts".into());
+    }
+    Ok((after, before))
+}
+
+/// Returns the fastest journey departing within the window, the one with less transfers on a tie.
+pub fn fastest_journey(
+    timetable: &Timetable,
+    after: NaiveTime,
+    before: NaiveTime,
+) -> Option<&Journey> {
+    timetable
+        .journeys
+        .iter()
+        .filter(|journey| {
+            parse_time(journey.departure())
+                .is_some_and(|departure| after <= departure && departure <= before)
+        })
+        .filter(|journey| journey.duration_minutes().is_some())
+        .min_by_key(|journey| (journey.duration_minutes(), journey.transfers()))
+}
+
+/// Requests the fastest journey of every origin and destination pair of the stations.
+pub async fn get_matrix(
+    client: &Client,
+    stations: &[Station],
+    date: NaiveDate,
+    window: (NaiveTime, NaiveTime),
+    concurrency: usize,
+    delay: Duration,
+) -> Vec<MatrixEntry> {
+    let trips: Vec<Trip> = stations
+        .iter()
+        .flat_map(|from| {
+            stations
+                .iter()
+                .filter(move |to| to.id != from.id)
+                .map(move |to| Trip {
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+        })
+        .collect();
+    let hour = format!("{:02}", window.0.hour());
+
+    let timetables = get_timetables(client, &trips, date, &hour, concurrency, delay).await;
+    trips
+        .into_iter()
+        .zip(timetables)
+        .map(|(trip, timetable)| MatrixEntry {
+            trip,
+            fastest: match timetable {
+                Ok(timetable) => Ok(fastest_journey(&timetable, window.0, window.1).cloned()),
+                // the site reports when there are no trains, which is the same as no journeys
+                Err(TimetableError::NoTrains(..)) => Ok(None),
+                Err(error) => Err(error.to_string()),
+            },
+        })
+        .collect()
+}
+
+/// Formats the fastest journey as its duration and transfers, like `01:58 (0)`.
+fn format_entry(entry: Option<&MatrixEntry>) -> String {
+    match entry.map(|entry| &entry.fastest) {
+        Some(Ok(Some(journey))) => format!("{} ({})", journey.duration, journey.transfers()),
+        Some(Ok(None)) => "-".to_string(),
+        Some(Err(..)) => "?".to_string(),
+        None => "".to_string(),
+    }
+}
+
+/// Builds the matrix table, origins being rows and destinations columns.
+///
+/// The last row has the longest duration to each destination, to tell the one reachable by everyone the soonest.
+pub fn build_matrix_table(stations: &[Station], entries: &[MatrixEntry]) -> Table {
+    let mut results_table = init_results_table();
+    let mut title_cells = vec![Cell::new("From \\ To")];
+    title_cells.extend(stations.iter().map(|station| Cell::new(&station.name)));
+    results_table.set_titles(Row::new(title_cells));
+
+    let entry = |from: &Station, to: &Station| {
+        entries
+            .iter()
+            .find(|entry| entry.trip.from.id == from.id && entry.trip.to.id == to.id)
+    };
+
+    for from in stations.iter() {
+        let mut cells = vec![Cell::new(&from.name).style_spec("b")];
+        cells.extend(
+            stations
+                .iter()
+                .map(|to| Cell::new(&format_entry(entry(from, to))).style_spec("c")),
+        );
+        results_table.add_row(Row::new(cells));
+    }
+
+    let mut cells = vec![Cell::new("Longest").style_spec("b")];
+    cells.extend(stations.iter().map(|to| {
+        let longest = stations
+            .iter()
+            .filter_map(|from| entry(from, to))
+            .filter_map(|entry| entry.fastest.as_ref().ok()?.as_ref())
+            .max_by_key(|journey| journey.duration_minutes())
+            .map_or("-".to_string(), |journey| journey.duration.clone());
+        Cell::new(&longest).style_spec("c")
+    }));
+    results_table.add_row(Row::new(cells));
+
+    results_table
+}
+
+/// Builds the table with one row per origin and destination pair, meant for CSV.
+pub fn build_matrix_rows(entries: &[MatrixEntry]) -> Table {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Origin"),
+        Cell::new("Destination"),
+        Cell::new("Duration"),
+        Cell::new("Transfers"),
+        Cell::new("Start"),
+        Cell::new("End"),
+        Cell::new("Train"),
+        Cell::new("Error"),
+    ]));
+
+    for entry in entries.iter() {
+        let mut cells = vec![
+            Cell::new(&entry.trip.from.name),
+            Cell::new(&entry.trip.to.name),
+        ];
+        match &entry.fastest {
+            Ok(Some(journey)) => cells.extend([
+                Cell::new(&journey.duration),
+                Cell::new(&journey.transfers().to_string()),
+                Cell::new(journey.departure()),
+                Cell::new(&journey.arrival_label()),
+                Cell::new(&journey.trains()),
+                Cell::new(""),
+            ]),
+            Ok(None) => cells.extend((0..6).map(|_| Cell::new(""))),
+            Err(error) => {
+                cells.extend((0..5).map(|_| Cell::new("")));
+                cells.push(Cell::new(error));
+            }
+        }
+        results_table.add_row(Row::new(cells));
+    }
+
+    results_table
+}
+
+/// Displays the fastest journey between every pair of the stations given by the CLI args.
+pub async fn search_matrix(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let values: Vec<&String> = args
+        .get_many::<String>("stations")
+        .map(|values| values.collect())
+        .unwrap_or_default();
+    let date = parse_query_date(args.get_one::<String>("date").map(|date| date.as_str()))?;
+    let window = parse_window(args)?;
+    let concurrency = *args.get_one::<u64>("concurrency").unwrap_or(&2) as usize;
+    let delay = Duration::from_millis(*args.get_one::<u64>("delay").unwrap_or(&500));
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+
+    let stations_list = get_stations_list(client).await?;
+    let mut stations: Vec<Station> = Vec::new();
+    for value in values {
+        let station = resolve_station(&stations_list, value)?;
+        if !stations.contains(&station) {
+            stations.push(station);
+        }
+    }
+    if stations.len() < 2 {
+        return Err("🚨 Please, specify at least two different stations".into());
+    }
+
+    if output == "table" {
+        println!(
+            "🗺 Searching the fastest journeys between {} stations on {} departing from {} to {} ({} queries)",
+            stations.len(),
+            format_date(&date),
+            window.0.format("%H:%M"),
+            window.1.format("%H:%M"),
+            stations.len() * (stations.len() - 1)
+        );
+    }
+    let entries = get_matrix(client, &stations, date, window, concurrency, delay).await;
+
+    match output {
+        "csv" => {
+            build_matrix_rows(&entries).to_csv(stdout())?;
+        }
+        _ => {
+            build_matrix_table(&stations, &entries).printstd();
+            let failed = entries
+                .iter()
+                .filter(|entry| entry.fastest.is_err())
+                .count();
+            if failed > 0 {
+                println!(
+                    "🕵️ {} queries failed and are shown as '?', no trains within the window are shown as '-'",
+                    failed
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::{station::station, timetable::fixture_timetable};
+
+    #[test]
+    fn test_fastest_journey_within_window() {
+        let timetable = fixture_timetable();
+        let time = |time: &str| parse_time(time).unwrap();
+
+        let fastest = fastest_journey(&timetable, time("00:00"), time("23:59")).unwrap();
+        assert_eq!(fastest.departure(), "21:19");
+        let fastest = fastest_journey(&timetable, time("06:00"), time("12:00")).unwrap();
+        assert_eq!(fastest.departure(), "06:04");
+        assert!(fastest_journey(&timetable, time("12:00"), time("13:00")).is_none());
+    }
+
+    /// Returns the fastest journey from Girona to Sitges, and an error the other way round.
+    fn matrix_entries() -> Vec<MatrixEntry> {
+        let timetable = fixture_timetable();
+        let (girona, sitges) = (station("79300", "Girona"), station("71701", "Sitges"));
+        vec![
+            MatrixEntry {
+                trip: Trip {
+                    from: girona.clone(),
+                    to: sitges.clone(),
+                },
+                fastest: Ok(Some(timetable.journeys[1].clone())),
+            },
+            MatrixEntry {
+                trip: Trip {
+                    from: sitges,
+                    to: girona,
+                },
+                fastest: Err("🚨 No trains".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_build_matrix_table() {
+        let stations = [station("79300", "Girona"), station("71701", "Sitges")];
+        let table = build_matrix_table(&stations, &matrix_entries());
+        assert_eq!(table.len(), 3);
+        assert_eq!(table[0][1].get_content(), "");
+        assert_eq!(table[0][2].get_content(), "01:58 (0)");
+        assert_eq!(table[1][1].get_content(), "?");
+        assert_eq!(table[2][1].get_content(), "-");
+        assert_eq!(table[2][2].get_content(), "01:58");
+    }
+
+    #[test]
+    fn test_build_matrix_rows() {
+        let rows = build_matrix_rows(&matrix_entries());
+        assert_eq!(rows[0][4].get_content(), "21:19");
+        assert_eq!(rows[1][7].get_content(), "🚨 No trains");
+    }
+}
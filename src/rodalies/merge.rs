@@ -0,0 +1,241 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::ArgMatches;
+use prettytable::{Cell, Row, Table};
+use std::{error::Error, time::Duration};
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, init_results_table},
+    rodalies::{
+        filter::{filter_timetable, JourneyFilter},
+        station::resolve_stations,
+        timetable::{get_timetables, sort_key, Journey, Timetable},
+        trip::Trip,
+    },
+};
+
+/// How many of the origin and destination pairs are requested at the same time.
+const MERGE_CONCURRENCY: usize = 4;
+
+/// A journey of one of the searched origin and destination pairs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergedJourney {
+    /// The origin and destination stations used.
+    pub trip: Trip,
+    /// The journey found between them.
+    pub journey: Journey,
+}
+
+/// Given a container of CLI args, it processes the repeatable `from` and `to` arguments.
+pub fn parse_trips(args: &ArgMatches) -> Result<(Vec<String>, Vec<String>), Box<dyn Error>> {
+    let values = |name: &str| -> Vec<String> {
+        args.get_many::<String>(name)
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
+    let (from, to) = (values("from"), values("to"));
+
+    if from.is_empty() || to.is_empty() {
+        return Err("🚨 Please, specify origin and destination station IDs".into());
+    }
+    Ok((from, to))
+}
+
+/// Fails when the CLI args ask for something the search of several origins or destinations cannot do.
+pub fn check_merged_args(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let unsupported = [
+        "via",
+        "arrive-by",
+        "tight-transfer",
+        "watch",
+        "notify-cmd",
+        "notify-before",
+    ];
+    match unsupported.iter().find(|name| args.contains_id(name)) {
+        Some(name) => Err(format!(
+            "🚨 Please, search several origins or destinations without '--{}'",
+            name
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Merges the journeys of all the pairs on the date into one list, ranked by arrival and then by latest departure.
+pub fn merge_journeys(timetables: &[(Trip, Timetable)], date: NaiveDate) -> Vec<MergedJourney> {
+    let mut journeys: Vec<MergedJourney> = timetables
+        .iter()
+        .flat_map(|(trip, timetable)| {
+            timetable.journeys.iter().map(|journey| MergedJourney {
+                trip: trip.clone(),
+                journey: journey.clone(),
+            })
+        })
+        .collect();
+
+    // journeys whose times cannot be read go last
+    let last = |time: Option<NaiveDateTime>| time.unwrap_or(NaiveDateTime::MAX);
+    journeys.sort_by(|a, b| {
+        last(a.journey.arrival_at(date))
+            .cmp(&last(b.journey.arrival_at(date)))
+            .then(
+                b.journey
+                    .departure_at(date)
+                    .cmp(&a.journey.departure_at(date)),
+            )
+    });
+    journeys
+}
+
+/// Builds the table of the merged journeys, labelled with the origin and destination each one uses.
+pub fn build_merged_table(journeys: &[MergedJourney]) -> Table {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Origin"),
+        Cell::new("Start"),
+        Cell::new("Destination"),
+        Cell::new("End"),
+        Cell::new("Duration"),
+        Cell::new("Train"),
+        Cell::new("Transfers"),
+    ]));
+
+    // the searched stations might be IDs, so the names are taken from the journeys
+    for merged in journeys.iter() {
+        let journey = &merged.journey;
+        results_table.add_row(Row::new(vec![
+            Cell::new(&journey.legs[0].origin),
+            Cell::new(merged.journey.departure()),
+            Cell::new(&journey.legs[journey.transfers()].destination),
+            Cell::new(&merged.journey.arrival_label()),
+            Cell::new(&merged.journey.duration),
+            Cell::new(&merged.journey.trains()),
+            Cell::new(&merged.journey.transfers().to_string()).style_spec("r"),
+        ]));
+    }
+
+    results_table
+}
+
+/// Searches every origin and destination pair and displays their journeys merged, ranked by arrival.
+pub async fn search_merged(
+    client: &Client,
+    from: Vec<String>,
+    to: Vec<String>,
+    date: NaiveDate,
+    filter: &JourneyFilter,
+    sort: Option<&str>,
+) -> Result<Vec<MergedJourney>, Box<dyn Error>> {
+    let mut values = from.clone();
+    values.extend(to.iter().cloned());
+    let stations = resolve_stations(client, &values).await?;
+    let (origins, destinations) = stations.split_at(from.len());
+
+    let trips: Vec<Trip> = origins
+        .iter()
+        .flat_map(|from| {
+            destinations
+                .iter()
+                .filter(move |to| to.id != from.id)
+                .map(move |to| Trip {
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+        })
+        .collect();
+    println!(
+        "🔀 Searching {} origin and destination pairs for date {}",
+        trips.len(),
+        format_date(&date)
+    );
+
+    let results = get_timetables(
+        client,
+        &trips,
+        date,
+        "00",
+        MERGE_CONCURRENCY,
+        Duration::ZERO,
+    )
+    .await;
+    let mut timetables = Vec::new();
+    for (trip, result) in trips.into_iter().zip(results) {
+        match result {
+            Ok(mut timetable) => {
+                filter_timetable(&mut timetable, filter);
+                timetables.push((trip, timetable));
+            }
+            Err(error) => println!(
+                "🕵️ No journeys from '{}' to '{}': {}",
+                trip.from.name, trip.to.name, error
+            ),
+        }
+    }
+
+    let mut journeys = merge_journeys(&timetables, date);
+    if journeys.is_empty() {
+        return Err(
+            "🚨 No journeys found for any of the origin and destination pairs, or left after applying the filters"
+                .into(),
+        );
+    }
+
+    match sort {
+        Some(sort) => {
+            // the ranking by arrival is kept on ties
+            journeys.sort_by_key(|merged| sort_key(&merged.journey, sort));
+            println!("📆 Listing the journeys of all pairs by {}", sort);
+        }
+        None => println!("📆 Listing the journeys of all pairs by arrival time"),
+    }
+    build_merged_table(&journeys).printstd();
+    Ok(journeys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::{
+        station::trip,
+        timetable::{fixture_date, fixture_timetable},
+    };
+
+    /// Merges the fixture with its morning journey leaving from another origin.
+    fn merged_journeys() -> Vec<MergedJourney> {
+        let timetable = fixture_timetable();
+        let mut flaca = Timetable {
+            journeys: vec![timetable.journeys[0].clone()],
+            ..timetable.clone()
+        };
+        flaca.journeys[0].legs[0].origin = "Flaçà".to_string();
+        let timetables = vec![
+            (trip("79300", "71701"), timetable),
+            (trip("79301", "71701"), flaca),
+        ];
+        merge_journeys(&timetables, fixture_date())
+    }
+
+    #[test]
+    fn test_merge_journeys_by_arrival() {
+        let journeys = merged_journeys();
+        assert_eq!(journeys.len(), 3);
+        assert_eq!(journeys[0].journey.arrival(), "08:22");
+        assert_eq!(journeys[2].journey.arrival(), "23:17");
+    }
+
+    #[test]
+    fn test_build_merged_table() {
+        let table = build_merged_table(&merged_journeys());
+        assert_eq!(table[0][0].get_content(), "Girona");
+        assert_eq!(table[0][2].get_content(), "Sitges");
+        assert_eq!(table[1][0].get_content(), "Flaçà");
+        assert_eq!(table[2][5].get_content(), "R11");
+    }
+
+    #[test]
+    fn test_sort_merged_journeys() {
+        let mut journeys = merged_journeys();
+        journeys.sort_by_key(|merged| sort_key(&merged.journey, "duration"));
+        assert_eq!(journeys[0].journey.departure(), "21:19");
+    }
+}
@@ -9,5 +9,53 @@ pub mod station;
 /// `timetable` is the module responsible to handle the processing, filtering and display of the desired trains' timetable.
 pub mod timetable;
 
+/// `arrive` is the module responsible to find the latest journeys arriving by a given time.
+pub mod arrive;
+/// `filter` is the module responsible to leave out the journeys whose transfers do not suit the user.
+pub mod filter;
+/// `line` is the module responsible to identify the train lines and their colours.
+pub mod line;
+/// `merge` is the module responsible to search several origins and destinations at once, ranking their journeys together.
+pub mod merge;
+/// `next` is the module responsible to find and display the next departures of a trip.
+pub mod next;
+/// `notify` is the module responsible to run the user's reminder command before a departure.
+pub mod notify;
+/// `risk` is the module responsible to warn about the transfers that might be missed.
+pub mod risk;
+/// `trip` is the module responsible to handle trips between two stations and the saved favourite ones.
+pub mod trip;
+/// `via` is the module responsible to plan trips stopping at other stations on the way, by chaining searches.
+pub mod via;
+/// `watch` is the module responsible to keep displaying the departures of a trip, refreshing them periodically.
+pub mod watch;
+
+/// `ends` is the module responsible to find the first and last journeys of the day of a trip.
+pub mod ends;
+
+/// `history` is the module responsible to keep, list and repeat the completed searches.
+pub mod history;
+
 /// `interactive` is the module responsible to handle the whole timetable search interactively.
 pub mod interactive;
+
+/// `tui` is the module responsible to handle the whole timetable search on a full-screen terminal UI.
+pub mod tui;
+
+/// `batch` is the module responsible to run many timetable queries from a file at once.
+pub mod batch;
+/// `matrix` is the module responsible to find the fastest journeys between every pair of a set of stations.
+pub mod matrix;
+/// `reach` is the module responsible to find where one can get from an origin, and how soon.
+pub mod reach;
+/// `snapshot` is the module responsible to keep the timetables of the trips and tell the trains changed between them.
+pub mod snapshot;
+/// `week` is the module responsible to compare the trains of a trip along a week.
+pub mod week;
+
+/// `api` is the module responsible to answer the stations, timetable and next departures queries as JSON, caching them in memory.
+pub mod api;
+/// `serve` is the module responsible to expose the `api` queries over HTTP.
+pub mod serve;
+/// `stdio` is the module responsible to expose the `api` queries as JSON-RPC over the standard input and output.
+pub mod stdio;
@@ -0,0 +1,316 @@
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, Timelike};
+use clap::ArgMatches;
+use prettytable::{Cell, Row};
+use serde_json::json;
+use std::error::Error;
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, init_results_table},
+    rodalies::{
+        filter::{parse_filter, JourneyFilter},
+        notify::{parse_notify, remind_departure},
+        timetable::{get_timetable, Journey, Timetable},
+        trip::{parse_trip_input, Trip},
+        watch::{parse_departure, parse_watch, watch_departures, WatchMode},
+    },
+};
+
+/// A journey departing from now on, with its actual departure date and time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NextDeparture {
+    /// When the journey departs.
+    pub departure: NaiveDateTime,
+    /// The departing journey.
+    pub journey: Journey,
+}
+
+impl NextDeparture {
+    /// The minutes left until the departure.
+    pub fn minutes_left(&self, now: NaiveDateTime) -> i64 {
+        (self.departure - now).num_minutes()
+    }
+}
+
+/// Returns the journeys of the given date with their actual departure date and time, keeping their order.
+pub fn dated_departures(journeys: &[Journey], date: NaiveDate) -> Vec<NextDeparture> {
+    journeys
+        .iter()
+        .filter_map(|journey| {
+            Some(NextDeparture {
+                departure: journey.departure_at(date)?,
+                journey: journey.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the journeys of the given date departing at or after `now`, keeping their order.
+pub fn upcoming_departures(
+    journeys: &[Journey],
+    date: NaiveDate,
+    now: NaiveDateTime,
+) -> Vec<NextDeparture> {
+    dated_departures(journeys, date)
+        .into_iter()
+        .filter(|departure| departure.departure >= now)
+        .collect()
+}
+
+/// Requests the next `count` departures of the trip from `now` on passing the filters, looking at tomorrow's timetable if needed.
+pub async fn get_next_departures(
+    client: &Client,
+    trip: &Trip,
+    now: NaiveDateTime,
+    count: usize,
+    filter: &JourneyFilter,
+) -> Result<Vec<NextDeparture>, Box<dyn Error>> {
+    let now = now.with_second(0).unwrap_or(now);
+    let today = now.date();
+    let upcoming = |timetable: &Timetable, date: NaiveDate| -> Vec<NextDeparture> {
+        upcoming_departures(&timetable.journeys, date, now)
+            .into_iter()
+            .filter(|departure| filter.matches(&departure.journey))
+            .collect()
+    };
+
+    // there might be no more trains today, which the site reports as an error
+    let mut today_error = None;
+    let mut departures = match get_timetable(
+        client,
+        trip.from.id.clone(),
+        trip.to.id.clone(),
+        format_date(&today),
+        format!("{:02}", now.hour()),
+    )
+    .await
+    {
+        Ok(timetable) => upcoming(&timetable, today),
+        Err(error) => {
+            today_error = Some(error);
+            Vec::new()
+        }
+    };
+
+    if departures.len() < count {
+        let tomorrow = today + Days::new(1);
+        match get_timetable(
+            client,
+            trip.from.id.clone(),
+            trip.to.id.clone(),
+            format_date(&tomorrow),
+            "00".to_string(),
+        )
+        .await
+        {
+            Ok(timetable) => departures.extend(upcoming(&timetable, tomorrow)),
+            Err(error) if departures.is_empty() => return Err(today_error.unwrap_or(error).into()),
+            Err(..) => {}
+        }
+    }
+
+    departures.truncate(count);
+    Ok(departures)
+}
+
+/// Formats the minutes left until a departure, like `5 min` or `1 h 05 min`.
+pub fn format_minutes_left(minutes: i64) -> String {
+    match minutes {
+        i64::MIN..=0 => "now".to_string(),
+        1..=59 => format!("{} min", minutes),
+        _ => format!("{} h {:02} min", minutes / 60, minutes % 60),
+    }
+}
+
+/// Displays the next departures of the trip given by the CLI args, with a countdown.
+///
+/// Besides a table, they can be displayed as a single line of text or as waybar's JSON for status bars.
+pub async fn search_next(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+    let count = *args.get_one::<usize>("count").unwrap_or(&3);
+    let now = Local::now().naive_local();
+    let filter = parse_filter(args)?;
+
+    if let Some(config) = parse_watch(args)? {
+        let trip = parse_trip_input(client, args).await?;
+        let mode = WatchMode::Next { count };
+        return watch_departures(client, &trip, mode, config, &filter).await;
+    }
+
+    if let Some(notify) = parse_notify(args)? {
+        let trip = parse_trip_input(client, args).await?;
+        let mode = WatchMode::Next { count };
+        let departure = parse_departure(args)?;
+        return remind_departure(client, &trip, mode, departure, notify, &filter).await;
+    }
+
+    let departures = match parse_trip_input(client, args).await {
+        Ok(trip) => get_next_departures(client, &trip, now, count, &filter).await,
+        Err(error) => Err(error),
+    };
+
+    match (output, departures) {
+        // waybar expects its JSON even when failing, to display the failure
+        ("waybar", Err(error)) => println!("{}", format_waybar_error(&error.to_string())),
+        (_, Err(error)) => return Err(error),
+        (_, Ok(departures)) if departures.is_empty() => {
+            return Err("🚨 No next departures found for the trip".into())
+        }
+        ("text", Ok(departures)) => println!("{}", format_status_line(&departures[0], now)),
+        ("waybar", Ok(departures)) => println!("{}", format_waybar(&departures, now)),
+        (_, Ok(departures)) => display_next_departures(&departures, now),
+    }
+
+    Ok(())
+}
+
+/// Displays a table with the next departures, with a countdown.
+fn display_next_departures(departures: &[NextDeparture], now: NaiveDateTime) {
+    let journey = &departures[0].journey;
+    println!(
+        "🚉 Next departures from '{}' to '{}'",
+        journey.legs[0].origin,
+        journey.legs[journey.transfers()].destination
+    );
+
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Start"),
+        Cell::new("End"),
+        Cell::new("Duration"),
+        Cell::new("Train"),
+        Cell::new("Leaves in"),
+    ]));
+    for departure in departures.iter() {
+        results_table.add_row(Row::new(vec![
+            Cell::new(&format_start(departure, now)),
+            Cell::new(&departure.journey.arrival_label()),
+            Cell::new(&departure.journey.duration),
+            Cell::new(&departure.journey.trains()),
+            Cell::new(&format_minutes_left(departure.minutes_left(now))).style_spec("r"),
+        ]));
+    }
+    results_table.printstd();
+}
+
+/// Formats the departure time, marking the ones of the next day with `(+1)`.
+fn format_start(departure: &NextDeparture, now: NaiveDateTime) -> String {
+    if departure.departure.date() == now.date() {
+        departure.journey.departure().to_string()
+    } else {
+        format!("{} (+1)", departure.journey.departure())
+    }
+}
+
+/// Formats the departure as a compact single line, like `🚆 18:19 R11 in 7 min`.
+pub fn format_status_line(departure: &NextDeparture, now: NaiveDateTime) -> String {
+    let minutes = departure.minutes_left(now);
+    format!(
+        "🚆 {} {} {}",
+        format_start(departure, now),
+        departure.journey.trains(),
+        if minutes > 0 {
+            format!("in {}", format_minutes_left(minutes))
+        } else {
+            format_minutes_left(minutes)
+        }
+    )
+}
+
+/// Formats the departures as waybar's custom module JSON, with `text`, `tooltip` and `class`.
+///
+/// The class is `soon` when the next train leaves in 5 minutes or less, `normal` otherwise.
+pub fn format_waybar(departures: &[NextDeparture], now: NaiveDateTime) -> String {
+    let journey = &departures[0].journey;
+    let mut tooltip = vec![format!(
+        "{} → {}",
+        journey.legs[0].origin,
+        journey.legs[journey.transfers()].destination
+    )];
+    for departure in departures.iter() {
+        tooltip.push(format!(
+            "{} → {} {} ({})",
+            format_start(departure, now),
+            departure.journey.arrival_label(),
+            departure.journey.trains(),
+            format_minutes_left(departure.minutes_left(now))
+        ));
+    }
+
+    json!({
+        "text": format_status_line(&departures[0], now),
+        "tooltip": tooltip.join("\n"),
+        "class": if departures[0].minutes_left(now) <= 5 { "soon" } else { "normal" },
+    })
+    .to_string()
+}
+
+/// Formats a failure as waybar's custom module JSON, with the `error` class.
+pub fn format_waybar_error(error: &str) -> String {
+    json!({
+        "text": "🚆 ?",
+        "tooltip": error,
+        "class": "error",
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    #[test]
+    fn test_upcoming_departures_from_now() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+        let now = date.and_hms_opt(6, 30, 0).unwrap();
+
+        let departures = upcoming_departures(&timetable.journeys, date, now);
+        assert_eq!(departures.len(), 1);
+        assert_eq!(departures[0].journey.departure(), "21:19");
+        assert_eq!(departures[0].minutes_left(now), 14 * 60 + 49);
+
+        let tomorrow = date.succ_opt().unwrap();
+        assert_eq!(
+            upcoming_departures(&timetable.journeys, tomorrow, now).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_format_minutes_left() {
+        assert_eq!(format_minutes_left(0), "now");
+        assert_eq!(format_minutes_left(7), "7 min");
+        assert_eq!(format_minutes_left(65), "1 h 05 min");
+    }
+
+    #[test]
+    fn test_status_bar_formats() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+        let now = date.and_hms_opt(21, 15, 0).unwrap();
+        let mut departures = upcoming_departures(&timetable.journeys, date, now);
+        departures.extend(upcoming_departures(
+            &timetable.journeys,
+            date.succ_opt().unwrap(),
+            now,
+        ));
+
+        assert_eq!(
+            format_status_line(&departures[0], now),
+            "🚆 21:19 R11 in 4 min"
+        );
+
+        let waybar: serde_json::Value =
+            serde_json::from_str(&format_waybar(&departures, now)).unwrap();
+        assert_eq!(waybar["text"], "🚆 21:19 R11 in 4 min");
+        assert_eq!(waybar["class"], "soon");
+        assert_eq!(
+            waybar["tooltip"],
+            "Girona → Sitges\n21:19 → 23:17 R11 (4 min)\n06:04 (+1) → 08:22 R11 → R2 (8 h 49 min)\n21:19 (+1) → 23:17 R11 (24 h 04 min)"
+        );
+    }
+}
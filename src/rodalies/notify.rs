@@ -0,0 +1,187 @@
+use chrono::{Local, NaiveDateTime, NaiveTime};
+use clap::ArgMatches;
+use std::{env, error::Error, time::Duration};
+use surf::Client;
+use tokio::process::Command;
+
+use crate::rodalies::{
+    filter::JourneyFilter,
+    next::{format_minutes_left, NextDeparture},
+    trip::Trip,
+    watch::{chosen_departure, get_watched_departures, WatchMode},
+};
+
+/// The environment variable with the default reminder command.
+const NOTIFY_CMD_VAR: &str = "RODALIES_CLI_NOTIFY_CMD";
+
+/// The departure reminder settings given by the CLI args.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotifyConfig {
+    /// The shell command to run as reminder.
+    pub command: String,
+    /// How many minutes before the departure the command is run.
+    pub before: i64,
+}
+
+/// Given a container of CLI args, it processes the `notify-cmd` and `notify-before` arguments.
+///
+/// The `RODALIES_CLI_NOTIFY_CMD` environment variable is only taken as command once `notify-before` asks for a reminder,
+/// so that having it set does not turn every search into one.
+pub fn parse_notify(args: &ArgMatches) -> Result<Option<NotifyConfig>, Box<dyn Error>> {
+    let before = args
+        .try_get_one::<i64>("notify-before")
+        .ok()
+        .flatten()
+        .copied();
+    let command = match args.try_get_one::<String>("notify-cmd").ok().flatten() {
+        Some(command) => command.to_string(),
+        None if before.is_some() => match env::var(NOTIFY_CMD_VAR) {
+            Ok(command) if !command.trim().is_empty() => command,
+            _ => {
+                return Err(format!(
+                    "🚨 Please, specify the reminder command with --notify-cmd or {}",
+                    NOTIFY_CMD_VAR
+                )
+                .into())
+            }
+        },
+        None => return Ok(None),
+    };
+
+    Ok(Some(NotifyConfig {
+        command,
+        before: before.unwrap_or(5),
+    }))
+}
+
+/// Returns the journey fields passed to the reminder command as environment variables.
+pub fn journey_env(departure: &NextDeparture, now: NaiveDateTime) -> Vec<(&'static str, String)> {
+    let journey = &departure.journey;
+    vec![
+        ("RODALIES_ORIGIN", journey.legs[0].origin.clone()),
+        (
+            "RODALIES_DESTINATION",
+            journey.legs[journey.transfers()].destination.clone(),
+        ),
+        (
+            "RODALIES_DATE",
+            departure.departure.format("%d/%m/%Y").to_string(),
+        ),
+        ("RODALIES_DEPARTURE", journey.departure().to_string()),
+        ("RODALIES_ARRIVAL", journey.arrival().to_string()),
+        (
+            "RODALIES_ARRIVAL_DATE",
+            journey
+                .arrival_at(departure.departure.date())
+                .map(|arrival| arrival.format("%d/%m/%Y").to_string())
+                .unwrap_or_default(),
+        ),
+        ("RODALIES_DURATION", journey.duration.clone()),
+        ("RODALIES_TRAIN", journey.trains()),
+        ("RODALIES_TRANSFERS", journey.transfers().to_string()),
+        (
+            "RODALIES_MINUTES_LEFT",
+            departure.minutes_left(now).to_string(),
+        ),
+    ]
+}
+
+/// Runs the reminder command through the system shell, with the journey fields as environment variables.
+pub async fn run_notify_command(
+    notify: &NotifyConfig,
+    departure: &NextDeparture,
+    now: NaiveDateTime,
+) -> Result<(), Box<dyn Error>> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    let status = command
+        .arg(&notify.command)
+        .envs(journey_env(departure, now))
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(format!("🚨 The reminder command failed with {}", status).into());
+    }
+    Ok(())
+}
+
+/// Waits until the given minutes before the chosen departure and then runs the reminder command.
+pub async fn remind_departure(
+    client: &Client,
+    trip: &Trip,
+    mode: WatchMode,
+    time: Option<NaiveTime>,
+    notify: NotifyConfig,
+    filter: &JourneyFilter,
+) -> Result<(), Box<dyn Error>> {
+    let now = Local::now().naive_local();
+    let departures = get_watched_departures(client, trip, &mode, now, filter).await?;
+    let departure = match chosen_departure(&departures, now, time) {
+        Some(departure) => departure.clone(),
+        None => return Err("🚨 No departure found to be reminded of".into()),
+    };
+
+    let wait = departure.minutes_left(now) - notify.before;
+    println!(
+        "⏰ You will be reminded of the {} {} train {} before it leaves, in {}",
+        departure.journey.departure(),
+        departure.journey.trains(),
+        format_minutes_left(notify.before),
+        format_minutes_left(wait)
+    );
+    if wait > 0 {
+        tokio::time::sleep(Duration::from_secs(wait as u64 * 60)).await;
+    }
+
+    run_notify_command(&notify, &departure, Local::now().naive_local()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::{
+        next::dated_departures,
+        timetable::{fixture_date, fixture_timetable},
+    };
+
+    #[test]
+    fn test_journey_env() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+        let departures = dated_departures(&timetable.journeys, date);
+        let now = date.and_hms_opt(5, 54, 0).unwrap();
+
+        let env = journey_env(&departures[0], now);
+        let get = |name: &str| env.iter().find(|(key, _)| *key == name).unwrap().1.as_str();
+        assert_eq!(get("RODALIES_ORIGIN"), "Girona");
+        assert_eq!(get("RODALIES_DESTINATION"), "Sitges");
+        assert_eq!(get("RODALIES_DATE"), "07/03/2024");
+        assert_eq!(get("RODALIES_DEPARTURE"), "06:04");
+        assert_eq!(get("RODALIES_TRAIN"), "R11 → R2");
+        assert_eq!(get("RODALIES_TRANSFERS"), "1");
+        assert_eq!(get("RODALIES_MINUTES_LEFT"), "10");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_notify_command_passes_journey_env() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+        let departures = dated_departures(&timetable.journeys, date);
+        let now = date.and_hms_opt(5, 54, 0).unwrap();
+        let notify = NotifyConfig {
+            command: r#"test "$RODALIES_DEPARTURE" = "06:04""#.to_string(),
+            before: 5,
+        };
+
+        assert!(tokio_test::block_on(run_notify_command(&notify, &departures[0], now)).is_ok());
+    }
+}
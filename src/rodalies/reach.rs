@@ -0,0 +1,371 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use clap::ArgMatches;
+use prettytable::{Cell, Row, Table};
+use serde_json::json;
+use std::{collections::BTreeMap, error::Error, fs, io::stdout, time::Duration};
+use surf::Client;
+
+use crate::{
+    config::{
+        cli::{format_date, init_results_table, parse_query_date},
+        store,
+    },
+    rodalies::{
+        matrix::parse_window,
+        station::{get_stations_list, resolve_station, Station},
+        timetable::{get_timetables, journey_json, parse_time, Journey, Timetable, TimetableError},
+        trip::Trip,
+    },
+};
+
+/// The earliest arriving journey found to a destination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reach {
+    /// The destination's station.
+    pub destination: Station,
+    /// The earliest arriving journey departing within the window.
+    pub journey: Journey,
+    /// When the journey arrives, which might be the day after.
+    pub arrival: NaiveDateTime,
+    /// The total duration of the journey in minutes.
+    pub duration: i64,
+}
+
+/// Returns the name of the data file caching the timetables from the origin on the date, from the hour.
+fn cache_file(origin: &Station, date: NaiveDate, hour: &str) -> String {
+    format!(
+        "reach-{}-{}-{}.json",
+        origin.id,
+        date.format("%Y-%m-%d"),
+        hour
+    )
+}
+
+/// Tells whether the data file is a cache of the timetables of a date before `today`.
+fn is_past_cache(name: &str, today: NaiveDate) -> bool {
+    // the name ends with the date and the hour, like `reach-79300-2024-03-07-06.json`
+    let date = name
+        .strip_prefix("reach-")
+        .and_then(|name| name.strip_suffix(".json"))
+        .and_then(|name| name.rsplit_once('-'))
+        .and_then(|(name, _)| name.get(name.len().checked_sub(10)?..));
+    date.and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .is_some_and(|date| date < today)
+}
+
+/// Removes the cached timetables of past dates, which are not searched anymore.
+fn remove_past_caches(today: NaiveDate) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(store::data_dir()?)? {
+        let entry = entry?;
+        let past = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| is_past_cache(name, today));
+        if past {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the earliest arriving journey departing within the window, with its arrival date and time.
+///
+/// Journeys whose duration cannot be read are left out, as they could not be sorted nor limited by it.
+pub fn earliest_arrival(
+    timetable: &Timetable,
+    date: NaiveDate,
+    after: NaiveTime,
+    before: NaiveTime,
+) -> Option<(Journey, NaiveDateTime)> {
+    timetable
+        .journeys
+        .iter()
+        .filter_map(|journey| {
+            let departure = parse_time(journey.departure())?;
+            if departure < after || departure > before {
+                return None;
+            }
+            journey.duration_minutes()?;
+            Some((journey.clone(), journey.arrival_at(date)?))
+        })
+        .min_by_key(|(journey, arrival)| (*arrival, journey.transfers()))
+}
+
+/// Sorts the reachable destinations by the given field: `arrival`, `duration`, `transfers` or `name`.
+pub fn sort_reaches(reaches: &mut [Reach], sort: &str) {
+    match sort {
+        "arrival" => reaches.sort_by_key(|reach| (reach.arrival, reach.duration)),
+        "transfers" => reaches.sort_by_key(|reach| (reach.journey.transfers(), reach.duration)),
+        "name" => reaches.sort_by(|a, b| a.destination.name.cmp(&b.destination.name)),
+        _ => reaches.sort_by_key(|reach| (reach.duration, reach.arrival)),
+    }
+}
+
+/// Requests the timetables from the origin to every other station, reusing the cached ones unless `refresh`.
+///
+/// The timetables found, and the trips without trains, are cached. Failed requests are tried again on the next run.
+/// The caches of past dates are removed meanwhile.
+pub async fn get_reach_timetables(
+    client: &Client,
+    origin: &Station,
+    stations_list: &[Station],
+    date: NaiveDate,
+    hour: &str,
+    refresh: bool,
+    politeness: (usize, Duration),
+) -> Result<(BTreeMap<String, Timetable>, usize), Box<dyn Error>> {
+    let file = cache_file(origin, date, hour);
+    let mut cached: BTreeMap<String, Timetable> = if refresh {
+        BTreeMap::new()
+    } else {
+        store::load(&file)?
+    };
+
+    let trips: Vec<Trip> = stations_list
+        .iter()
+        .filter(|station| station.id != origin.id && !cached.contains_key(&station.id))
+        .map(|station| Trip {
+            from: origin.clone(),
+            to: station.clone(),
+        })
+        .collect();
+    let (concurrency, delay) = politeness;
+    let timetables = get_timetables(client, &trips, date, hour, concurrency, delay).await;
+
+    let mut failed = 0;
+    for (trip, timetable) in trips.into_iter().zip(timetables) {
+        match timetable {
+            Ok(timetable) => {
+                cached.insert(trip.to.id, timetable);
+            }
+            // the site reports when there are no trains, which is worth caching too
+            Err(TimetableError::NoTrains(..)) => {
+                cached.insert(trip.to.id, Timetable::default());
+            }
+            Err(TimetableError::Failed(..)) => failed += 1,
+        }
+    }
+
+    // it is not part of the results, which might be read by other programs
+    let today = Local::now().date_naive();
+    if let Err(error) = store::save(&file, &cached).and_then(|_| remove_past_caches(today)) {
+        eprintln!("🕵️ Could not cache the timetables: {}", error);
+    }
+    Ok((cached, failed))
+}
+
+/// Builds the table of the reachable destinations.
+pub fn build_reach_table(reaches: &[Reach]) -> Table {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Destination"),
+        Cell::new("Start"),
+        Cell::new("End"),
+        Cell::new("Duration"),
+        Cell::new("Transfers"),
+        Cell::new("Train"),
+    ]));
+
+    for reach in reaches.iter() {
+        results_table.add_row(Row::new(vec![
+            Cell::new(&reach.destination.name),
+            Cell::new(reach.journey.departure()),
+            Cell::new(&reach.journey.arrival_label()),
+            Cell::new(&reach.journey.duration),
+            Cell::new(&reach.journey.transfers().to_string()).style_spec("r"),
+            Cell::new(&reach.journey.trains()),
+        ]));
+    }
+
+    results_table
+}
+
+/// Displays where one can get from the origin given by the CLI args, departing within the window.
+pub async fn search_reach(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let date = parse_query_date(args.get_one::<String>("date").map(|date| date.as_str()))?;
+    let (after, before) = parse_window(args)?;
+    let within = args.get_one::<i64>("within").copied();
+    let sort = args
+        .get_one::<String>("sort")
+        .map_or("duration", |sort| sort.as_str());
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+    let concurrency = *args.get_one::<u64>("concurrency").unwrap_or(&2) as usize;
+    let delay = Duration::from_millis(*args.get_one::<u64>("delay").unwrap_or(&500));
+
+    let stations_list = get_stations_list(client).await?;
+    let origin = match args.get_one::<String>("origin") {
+        Some(origin) => resolve_station(&stations_list, origin)?,
+        None => return Err("🚨 Please, specify the origin station".into()),
+    };
+    let hour = format!("{:02}", after.hour());
+
+    if output == "table" {
+        println!(
+            "🧭 Searching where to get from '{}' on {} departing from {} to {}, this might take a while the first time",
+            origin.name,
+            format_date(&date),
+            after.format("%H:%M"),
+            before.format("%H:%M")
+        );
+    }
+    let (timetables, failed) = get_reach_timetables(
+        client,
+        &origin,
+        &stations_list,
+        date,
+        &hour,
+        args.get_flag("refresh"),
+        (concurrency, delay),
+    )
+    .await?;
+
+    let mut reaches: Vec<Reach> = stations_list
+        .iter()
+        .filter_map(|station| {
+            let (journey, arrival) =
+                earliest_arrival(timetables.get(&station.id)?, date, after, before)?;
+            Some(Reach {
+                destination: station.clone(),
+                duration: journey.duration_minutes()?,
+                journey,
+                arrival,
+            })
+        })
+        .filter(|reach| match within {
+            Some(within) => reach.duration <= within,
+            None => true,
+        })
+        .collect();
+    sort_reaches(&mut reaches, sort);
+
+    match output {
+        "csv" => {
+            build_reach_table(&reaches).to_csv(stdout())?;
+        }
+        "json" => {
+            let reaches: Vec<_> = reaches
+                .iter()
+                .map(|reach| {
+                    json!({
+                        "destination": reach.destination,
+                        "arrival": reach.arrival.format("%Y-%m-%dT%H:%M").to_string(),
+                        "duration_minutes": reach.duration,
+                        "transfers": reach.journey.transfers(),
+                        "journey": journey_json(&reach.journey, date),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&reaches)?);
+        }
+        _ => {
+            println!("📍 {} stations can be reached", reaches.len());
+            build_reach_table(&reaches).printstd();
+            if failed > 0 {
+                println!(
+                    "🕵️ {} destinations could not be requested, run it again to retry them",
+                    failed
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::{
+        station::station,
+        timetable::{fixture_date, fixture_timetable},
+    };
+
+    fn time(time: &str) -> NaiveTime {
+        parse_time(time).unwrap()
+    }
+
+    fn reach(timetable: &Timetable, name: &str, index: usize) -> Reach {
+        let journey = timetable.journeys[index].clone();
+        let single = Timetable {
+            journeys: vec![journey.clone()],
+            ..timetable.clone()
+        };
+        let (_, arrival) =
+            earliest_arrival(&single, fixture_date(), time("00:00"), time("23:59")).unwrap();
+        Reach {
+            destination: station(name, name),
+            arrival,
+            duration: journey.duration_minutes().unwrap(),
+            journey,
+        }
+    }
+
+    #[test]
+    fn test_earliest_arrival_within_window() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+
+        let (journey, arrival) =
+            earliest_arrival(&timetable, date, time("00:00"), time("23:59")).unwrap();
+        assert_eq!(journey.departure(), "06:04");
+        assert_eq!(arrival, date.and_hms_opt(8, 22, 0).unwrap());
+        let (journey, _) =
+            earliest_arrival(&timetable, date, time("07:00"), time("23:59")).unwrap();
+        assert_eq!(journey.departure(), "21:19");
+        assert!(earliest_arrival(&timetable, date, time("22:00"), time("23:59")).is_none());
+    }
+
+    #[test]
+    fn test_earliest_arrival_skips_unreadable_durations() {
+        let mut timetable = fixture_timetable();
+        timetable.journeys[0].duration = "--".to_string();
+        let (journey, _) =
+            earliest_arrival(&timetable, fixture_date(), time("00:00"), time("23:59")).unwrap();
+        assert_eq!(journey.departure(), "21:19");
+    }
+
+    #[test]
+    fn test_sort_reaches() {
+        let timetable = fixture_timetable();
+        let mut reaches = vec![
+            reach(&timetable, "Sitges", 0),
+            reach(&timetable, "Calafell", 1),
+        ];
+
+        sort_reaches(&mut reaches, "duration");
+        assert_eq!(reaches[0].destination.name, "Calafell");
+        sort_reaches(&mut reaches, "arrival");
+        assert_eq!(reaches[0].destination.name, "Sitges");
+        sort_reaches(&mut reaches, "transfers");
+        assert_eq!(reaches[0].destination.name, "Calafell");
+        sort_reaches(&mut reaches, "name");
+        assert_eq!(reaches[0].destination.name, "Calafell");
+    }
+
+    #[test]
+    fn test_build_reach_table() {
+        let timetable = fixture_timetable();
+        let reaches = vec![
+            reach(&timetable, "Sitges", 0),
+            reach(&timetable, "Calafell", 1),
+        ];
+        let table = build_reach_table(&reaches);
+        assert_eq!(table[0][4].get_content(), "1");
+        assert_eq!(table[1][4].get_content(), "0");
+    }
+
+    #[test]
+    fn test_is_past_cache() {
+        let today = fixture_date();
+        assert!(is_past_cache("reach-79300-2024-03-06-06.json", today));
+        assert!(!is_past_cache("reach-79300-2024-03-07-06.json", today));
+        assert!(!is_past_cache("reach-79300-2024-03-08-00.json", today));
+        assert!(!is_past_cache("history.json", today));
+        assert!(!is_past_cache(
+            "snapshot-79300-71701-2024-03-06.json",
+            today
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use crate::{
+    config::cli::parse_minutes,
+    rodalies::timetable::{Journey, Timetable},
+};
+
+/// The minutes to change trains below which a transfer is risky, when none is given.
+pub const TIGHT_TRANSFER: i64 = 5;
+
+/// The stations where changing between line families usually means walking to a far platform.
+const LARGE_STATIONS: [&str; 10] = [
+    "Barcelona-Sants",
+    "Barcelona-Passeig de Gràcia",
+    "Barcelona-Estació de França",
+    "Barcelona-El Clot-Aragó",
+    "Barcelona-La Sagrera-Meridiana",
+    "L'Hospitalet de Llobregat",
+    "Girona",
+    "Tarragona",
+    "Reus",
+    "Lleida-Pirineus",
+];
+
+/// Why a transfer might be missed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RiskReason {
+    /// The time to change trains is below the threshold, or cannot be read.
+    TightWait,
+    /// The transfer changes between line families at a large station.
+    LineChange,
+}
+
+/// A transfer of a journey that might be missed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionRisk {
+    /// The position of the transfer on the journey, starting at 0.
+    pub transfer: usize,
+    /// The station name where trains are changed.
+    pub station: String,
+    /// The minutes to change trains, if its text could be read.
+    pub wait: Option<i64>,
+    /// Why the transfer might be missed.
+    pub reasons: Vec<RiskReason>,
+}
+
+/// Given a container of CLI args, it processes the `tight-transfer` argument.
+pub fn parse_tight_transfer(args: &ArgMatches) -> Result<i64, Box<dyn Error>> {
+    match args.try_get_one::<String>("tight-transfer") {
+        Ok(Some(value)) => parse_minutes(value),
+        _ => Ok(TIGHT_TRANSFER),
+    }
+}
+
+/// Tells whether the station is one of the large ones, regardless of case.
+fn is_large_station(station: &str) -> bool {
+    LARGE_STATIONS
+        .iter()
+        .any(|large| large.eq_ignore_ascii_case(station.trim()))
+}
+
+/// Returns the transfers of the journey that might be missed, waiting less than `tight` minutes or changing line families at a large station.
+pub fn assess_risks(journey: &Journey, tight: i64) -> Vec<ConnectionRisk> {
+    journey
+        .wait_minutes()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(transfer, wait)| {
+            let station = &journey.legs[transfer].destination;
+            let mut reasons = Vec::new();
+            // a wait that cannot be read is not trusted to be long enough
+            let tight_wait = match wait {
+                Some(wait) => wait < tight,
+                None => true,
+            };
+            if tight_wait {
+                reasons.push(RiskReason::TightWait);
+            }
+            let families = (
+                journey.legs[transfer].line(),
+                journey.legs[transfer + 1].line(),
+            );
+            if let (Some(arriving), Some(leaving)) = families {
+                if arriving.service() != leaving.service() && is_large_station(station) {
+                    reasons.push(RiskReason::LineChange);
+                }
+            }
+
+            if reasons.is_empty() {
+                return None;
+            }
+            Some(ConnectionRisk {
+                transfer,
+                station: station.clone(),
+                wait,
+                reasons,
+            })
+        })
+        .collect()
+}
+
+/// Assesses again the connection risks of every journey of the timetable, with the given threshold.
+pub fn mark_risks(timetable: &mut Timetable, tight: i64) {
+    for journey in timetable.journeys.iter_mut() {
+        journey.risks = assess_risks(journey, tight);
+    }
+}
+
+/// Returns the warning telling how many journeys have risky connections, and where.
+pub fn build_risks_warning(timetable: &Timetable) -> Option<String> {
+    let risky: Vec<&Journey> = timetable
+        .journeys
+        .iter()
+        .filter(|journey| !journey.risks.is_empty())
+        .collect();
+    if risky.is_empty() {
+        return None;
+    }
+
+    let mut stations: Vec<&str> = risky
+        .iter()
+        .flat_map(|journey| journey.risks.iter().map(|risk| risk.station.as_str()))
+        .collect();
+    stations.sort();
+    stations.dedup();
+    Some(format!(
+        "⚠️ {} journeys have connections that might be missed, at {}. They are marked with ⚠ on their wait.",
+        risky.len(),
+        stations.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::fixture_timetable;
+
+    #[test]
+    fn test_assess_line_change_risk() {
+        let timetable = fixture_timetable();
+        let journey = &timetable.journeys[0];
+        // the R11 regional to R2 commuter change at Barcelona-Sants is risky whatever the wait
+        let risks = assess_risks(journey, TIGHT_TRANSFER);
+        assert_eq!(risks.len(), 1);
+        assert_eq!(risks[0].station, "Barcelona-Sants");
+        assert_eq!(risks[0].wait, Some(5));
+        assert_eq!(risks[0].reasons, vec![RiskReason::LineChange]);
+        assert_eq!(journey.risks, risks);
+        assert!(assess_risks(&timetable.journeys[1], 60).is_empty());
+    }
+
+    #[test]
+    fn test_assess_tight_wait_risk() {
+        let timetable = fixture_timetable();
+        let risks = assess_risks(&timetable.journeys[0], 10);
+        assert_eq!(
+            risks[0].reasons,
+            vec![RiskReason::TightWait, RiskReason::LineChange]
+        );
+    }
+
+    #[test]
+    fn test_assess_risks_within_line_family() {
+        let mut same_family = fixture_timetable().journeys[0].clone();
+        same_family.legs[1].train = "R11".to_string();
+        assert!(assess_risks(&same_family, TIGHT_TRANSFER).is_empty());
+        same_family.waits[0] = "2 min".to_string();
+        assert_eq!(
+            assess_risks(&same_family, TIGHT_TRANSFER)[0].reasons,
+            vec![RiskReason::TightWait]
+        );
+    }
+
+    #[test]
+    fn test_build_risks_warning() {
+        assert_eq!(
+            build_risks_warning(&fixture_timetable()).unwrap(),
+            "⚠️ 1 journeys have connections that might be missed, at Barcelona-Sants. They are marked with ⚠ on their wait."
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use clap::ArgMatches;
+use serde_json::{json, Value};
+use std::{collections::HashMap, error::Error, io, sync::Arc, time::Duration};
+use surf::{Client, Url};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::rodalies::{
+    api::{Api, ApiRequest},
+    stdio::serve_stdio,
+};
+
+/// How long a client has to send its request before the connection is closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum size in bytes of the request line and headers.
+const MAX_REQUEST_SIZE: u64 = 16 * 1024;
+
+/// How long to wait before accepting connections again after failing to, e.g. when running out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Serves the stations, timetable and next departures as JSON over HTTP, or over stdio, until interrupted.
+///
+/// Every connection is answered on its own task, sharing the in-memory cache of the stations list and timetables.
+pub async fn serve(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let host = args
+        .get_one::<String>("host")
+        .map_or("127.0.0.1", |host| host.as_str());
+    let port = *args.get_one::<u16>("port").unwrap_or(&3000);
+    let ttl = Duration::from_secs(*args.get_one::<u64>("cache-ttl").unwrap_or(&300));
+
+    if args.get_flag("stdio") {
+        return serve_stdio(client, ttl).await;
+    }
+
+    let listener = TcpListener::bind((host, port)).await?;
+    println!(
+        "🌐 Serving the timetables API on http://{}, press Ctrl+C to stop.",
+        listener.local_addr()?
+    );
+
+    let api = Arc::new(Api::new(client.clone(), ttl));
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                // the server keeps running, as the failure might be transient
+                println!("🚨 Could not accept a connection: {}", error);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let api = api.clone();
+        tokio::spawn(async move {
+            if let Err(error) = handle_connection(&api, stream).await {
+                println!("🚨 Could not answer a request: {}", error);
+            }
+        });
+    }
+}
+
+/// Reads the HTTP request line, skipping the headers, or returns `None` when they are larger than allowed.
+async fn read_request_line<R: AsyncRead + Unpin>(read: R) -> io::Result<Option<String>> {
+    let mut reader = BufReader::new(read.take(MAX_REQUEST_SIZE));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // the headers are not needed, but they must be read before answering
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            // the client stopped sending, or the size limit was reached
+            if reader.get_ref().limit() == 0 {
+                return Ok(None);
+            }
+            break;
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+    }
+    Ok(Some(request_line))
+}
+
+/// Reads the HTTP request from the connection and writes back the JSON response.
+async fn handle_connection(api: &Api, mut stream: TcpStream) -> io::Result<()> {
+    let (read, mut write) = stream.split();
+
+    let (status, body) = match timeout(REQUEST_TIMEOUT, read_request_line(read)).await {
+        Ok(Ok(Some(request_line))) => route(api, &request_line).await,
+        Ok(Ok(None)) => (431, error_json("🚨 The request is too large")),
+        Ok(Err(error)) => return Err(error),
+        Err(..) => (408, error_json("🚨 The request took too long to be sent")),
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        status_text(status),
+        body.len(),
+        body
+    );
+    write.write_all(response.as_bytes()).await?;
+    write.flush().await?;
+    Ok(())
+}
+
+/// Answers the request given its HTTP request line, returning the HTTP status and the JSON body.
+pub async fn route(api: &Api, request_line: &str) -> (u16, Value) {
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return (400, error_json("🚨 Malformed HTTP request")),
+    };
+    if method != "GET" {
+        return (405, error_json("🚨 Only GET requests are supported"));
+    }
+
+    let url = match Url::parse(&format!("http://localhost{}", target)) {
+        Ok(url) => url,
+        Err(..) => return (400, error_json("🚨 Malformed request path")),
+    };
+    let name = match url.path() {
+        "/stations" => "stations",
+        "/timetable" => "timetable",
+        "/next" => "next",
+        path => return (404, error_json(&format!("🚨 Unknown path '{}'", path))),
+    };
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    let request = match ApiRequest::parse(name, &params) {
+        Ok(request) => request,
+        Err(error) => return (400, error_json(&error.to_string())),
+    };
+    match api.answer(request).await {
+        Ok(body) => (200, body),
+        Err(error) => (500, error_json(&error.to_string())),
+    }
+}
+
+/// Formats the error as a JSON body.
+fn error_json(error: &str) -> Value {
+    json!({ "error": error })
+}
+
+/// Returns the reason phrase of the HTTP statuses answered.
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::client::init_client;
+
+    #[test]
+    fn test_route_rejects_wrong_requests() {
+        let api = Api::new(init_client(), Duration::from_secs(60));
+
+        let (status, body) = tokio_test::block_on(route(&api, "GET /unknown HTTP/1.1"));
+        assert_eq!(status, 404);
+        assert_eq!(body["error"], "🚨 Unknown path '/unknown'");
+
+        let (status, _) = tokio_test::block_on(route(&api, "POST /next HTTP/1.1"));
+        assert_eq!(status, 405);
+
+        let (status, body) = tokio_test::block_on(route(&api, "GET /next?from=79300 HTTP/1.1"));
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "🚨 Please, specify the 'to' parameter");
+
+        let (status, _) = tokio_test::block_on(route(&api, ""));
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn test_read_request_line_limits_its_size() {
+        let request = "GET /next?from=79300&to=79202 HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let request_line = tokio_test::block_on(read_request_line(request.as_bytes())).unwrap();
+        assert_eq!(
+            request_line.unwrap().trim(),
+            "GET /next?from=79300&to=79202 HTTP/1.1"
+        );
+
+        let large = format!("GET / HTTP/1.1\r\nCookie: {}\r\n\r\n", "a".repeat(20_000));
+        let request_line = tokio_test::block_on(read_request_line(large.as_bytes())).unwrap();
+        assert!(request_line.is_none());
+    }
+}
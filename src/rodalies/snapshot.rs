@@ -0,0 +1,368 @@
+use chrono::{Local, NaiveDate};
+use clap::ArgMatches;
+use prettytable::{Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::error::Error;
+use surf::Client;
+
+use crate::{
+    config::{
+        cli::{format_date, init_results_table, parse_query_date},
+        store,
+    },
+    rodalies::{
+        timetable::{get_timetable, journey_json, Journey, Timetable, TimetableError},
+        trip::{parse_trip_input, Trip},
+    },
+};
+
+/// The maximum minutes a train can be moved to be taken as retimed, instead of removed and added.
+const RETIME_WINDOW: i64 = 30;
+
+/// The timetable of a trip on a date, as it was fetched at some point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The searched trip.
+    pub trip: Trip,
+    /// The searched date (`dd/mm/yyyy`).
+    pub date: String,
+    /// When the timetable was fetched (`dd/mm/yyyy HH:MM`).
+    pub fetched: String,
+    /// The fetched timetable.
+    pub timetable: Timetable,
+}
+
+/// A change of the trains of a timetable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(Journey),
+    Removed(Journey),
+    Retimed { before: Journey, after: Journey },
+}
+
+/// Returns the name of the data file keeping the snapshot of the trip on the date.
+fn snapshot_file(trip: &Trip, date: NaiveDate) -> String {
+    format!(
+        "snapshot-{}-{}-{}.json",
+        trip.from.id,
+        trip.to.id,
+        date.format("%Y-%m-%d")
+    )
+}
+
+/// Loads the stored snapshot of the trip on the date, if any.
+pub fn load_snapshot(trip: &Trip, date: NaiveDate) -> Result<Option<Snapshot>, Box<dyn Error>> {
+    store::load(&snapshot_file(trip, date))
+}
+
+/// Stores the timetable as the snapshot of the trip on the date, replacing the previous one.
+pub fn save_snapshot(trip: &Trip, date: NaiveDate, timetable: &Timetable) {
+    let snapshot = Snapshot {
+        trip: trip.clone(),
+        date: format_date(&date),
+        fetched: Local::now().format("%d/%m/%Y %H:%M").to_string(),
+        timetable: timetable.clone(),
+    };
+    if let Err(error) = store::save(&snapshot_file(trip, date), &Some(snapshot)) {
+        eprintln!("🕵️ Could not store the timetable snapshot: {}", error);
+    }
+}
+
+/// Compares two timetables of a trip, telling the added, removed and retimed trains in departure order.
+///
+/// Trains are matched by their lines and departure time. Unmatched ones taking the same lines and departing
+/// within 30 minutes of each other are taken as retimed.
+pub fn diff_timetables(before: &Timetable, after: &Timetable, date: NaiveDate) -> Vec<Change> {
+    let mut removed: Vec<&Journey> = Vec::new();
+    let mut added: Vec<&Journey> = after.journeys.iter().collect();
+    let mut changes = Vec::new();
+
+    for old in before.journeys.iter() {
+        let same = added
+            .iter()
+            .position(|new| new.trains() == old.trains() && new.departure() == old.departure());
+        match same {
+            Some(position) => {
+                let new = added.remove(position);
+                if new.arrival() != old.arrival() {
+                    changes.push(Change::Retimed {
+                        before: old.clone(),
+                        after: new.clone(),
+                    });
+                }
+            }
+            None => removed.push(old),
+        }
+    }
+
+    for old in removed {
+        let minutes_apart = |new: &Journey| {
+            Some(
+                (new.departure_at(date)? - old.departure_at(date)?)
+                    .num_minutes()
+                    .abs(),
+            )
+        };
+        let closest = added
+            .iter()
+            .enumerate()
+            .filter(|(_, new)| new.trains() == old.trains())
+            .filter_map(|(position, new)| Some((minutes_apart(new)?, position)))
+            .filter(|(minutes, _)| *minutes <= RETIME_WINDOW)
+            .min();
+        match closest {
+            Some((_, position)) => changes.push(Change::Retimed {
+                before: old.clone(),
+                after: added.remove(position).clone(),
+            }),
+            None => changes.push(Change::Removed(old.clone())),
+        }
+    }
+    changes.extend(added.into_iter().map(|new| Change::Added(new.clone())));
+
+    changes.sort_by_key(|change| match change {
+        Change::Added(journey) | Change::Removed(journey) => journey.departure_at(date),
+        Change::Retimed { before, .. } => before.departure_at(date),
+    });
+    changes
+}
+
+/// Formats the journey as its departure and arrival times, like `06:04 → 08:22`.
+fn format_times(journey: &Journey) -> String {
+    format!("{} → {}", journey.departure(), journey.arrival_label())
+}
+
+/// Builds the table of the timetable changes.
+pub fn build_diff_table(changes: &[Change]) -> Table {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Change"),
+        Cell::new("Train"),
+        Cell::new("Before"),
+        Cell::new("After"),
+    ]));
+
+    for change in changes.iter() {
+        let (label, train, before, after) = match change {
+            Change::Added(journey) => ("➕ Added", journey, String::new(), format_times(journey)),
+            Change::Removed(journey) => {
+                ("➖ Removed", journey, format_times(journey), String::new())
+            }
+            Change::Retimed { before, after } => (
+                "🕒 Retimed",
+                after,
+                format_times(before),
+                format_times(after),
+            ),
+        };
+        results_table.add_row(Row::new(vec![
+            Cell::new(label),
+            Cell::new(&train.trains()),
+            Cell::new(&before),
+            Cell::new(&after),
+        ]));
+    }
+
+    results_table
+}
+
+/// Returns the timetable changes as JSON, with the journeys before and after each one.
+pub fn diff_json(changes: &[Change], before_date: NaiveDate, after_date: NaiveDate) -> Value {
+    let changes: Vec<Value> = changes
+        .iter()
+        .map(|change| match change {
+            Change::Added(journey) => json!({
+                "change": "added",
+                "after": journey_json(journey, after_date),
+            }),
+            Change::Removed(journey) => json!({
+                "change": "removed",
+                "before": journey_json(journey, before_date),
+            }),
+            Change::Retimed { before, after } => json!({
+                "change": "retimed",
+                "before": journey_json(before, before_date),
+                "after": journey_json(after, after_date),
+            }),
+        })
+        .collect();
+    json!(changes)
+}
+
+/// Requests the timetable of the trip on the whole date.
+///
+/// When the site reports there are no trains, as when works cut a line, the timetable is empty so they all show as removed.
+async fn fetch_timetable(
+    client: &Client,
+    trip: &Trip,
+    date: NaiveDate,
+) -> Result<Timetable, Box<dyn Error>> {
+    match get_timetable(
+        client,
+        trip.from.id.clone(),
+        trip.to.id.clone(),
+        format_date(&date),
+        "00".to_string(),
+    )
+    .await
+    {
+        Ok(timetable) => Ok(timetable),
+        Err(TimetableError::NoTrains(..)) => Ok(Timetable::default()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Displays the changes of the timetable of the trip given by the CLI args since its stored snapshot, or between two dates.
+pub async fn search_diff(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let date = parse_query_date(args.get_one::<String>("date").map(|date| date.as_str()))?;
+    let against = match args.get_one::<String>("against") {
+        Some(against) => Some(parse_query_date(Some(against))?),
+        None => None,
+    };
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+    let trip = parse_trip_input(client, args).await?;
+
+    let (before, after, after_date) = match against {
+        Some(against) => {
+            if output == "table" {
+                println!(
+                    "🔎 Comparing the timetables from '{}' to '{}' of {} and {}",
+                    trip.from.name,
+                    trip.to.name,
+                    format_date(&date),
+                    format_date(&against)
+                );
+            }
+            // a one-off comparison does not replace the stored snapshots
+            let before = fetch_timetable(client, &trip, date).await?;
+            let after = fetch_timetable(client, &trip, against).await?;
+            (before, after, against)
+        }
+        None => {
+            let snapshot = load_snapshot(&trip, date)?;
+            let after = fetch_timetable(client, &trip, date).await?;
+            // a day without trains is not kept, so later runs still compare with the last known trains
+            if !after.journeys.is_empty() {
+                save_snapshot(&trip, date, &after);
+            }
+            let snapshot = match snapshot {
+                Some(snapshot) => snapshot,
+                None => {
+                    if output != "table" {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&diff_json(&[], date, date))?
+                        );
+                    } else if after.journeys.is_empty() {
+                        println!(
+                            "📸 There are no trains from '{}' to '{}' on {} to store as the first snapshot",
+                            trip.from.name,
+                            trip.to.name,
+                            format_date(&date)
+                        );
+                    } else {
+                        println!(
+                            "📸 Stored the first snapshot from '{}' to '{}' on {}, run it again to find changes",
+                            trip.from.name,
+                            trip.to.name,
+                            format_date(&date)
+                        );
+                    }
+                    return Ok(());
+                }
+            };
+            if output == "table" {
+                println!(
+                    "🔎 Comparing the timetable from '{}' to '{}' on {} with its snapshot of {}",
+                    trip.from.name,
+                    trip.to.name,
+                    format_date(&date),
+                    snapshot.fetched
+                );
+            }
+            (snapshot.timetable, after, date)
+        }
+    };
+
+    let changes = diff_timetables(&before, &after, date);
+    match output {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&diff_json(&changes, date, after_date))?
+        ),
+        _ if changes.is_empty() => println!("✅ No trains were added, removed or retimed"),
+        _ => {
+            println!("📢 {} trains changed", changes.len());
+            build_diff_table(&changes).printstd();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    /// Returns the changes when the morning train leaves 10 minutes later, and the evening one
+    /// is replaced by an R2 one.
+    fn changes() -> Vec<Change> {
+        let before = fixture_timetable();
+        let mut after = before.clone();
+        after.journeys[0].legs[0].departure = "06:14".to_string();
+        after.journeys[1].legs[0].train = "R2".to_string();
+        diff_timetables(&before, &after, fixture_date())
+    }
+
+    #[test]
+    fn test_diff_same_timetables() {
+        let timetable = fixture_timetable();
+        assert!(diff_timetables(&timetable, &timetable, fixture_date()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_timetable_without_trains() {
+        // a date without trains removes all of them
+        let changes = diff_timetables(&fixture_timetable(), &Timetable::default(), fixture_date());
+        assert_eq!(changes.len(), 2);
+        assert!(changes
+            .iter()
+            .all(|change| matches!(change, Change::Removed(..))));
+    }
+
+    #[test]
+    fn test_diff_retimed_and_replaced_trains() {
+        let changes = changes();
+        assert_eq!(changes.len(), 3);
+        match &changes[0] {
+            Change::Retimed { before, after } => {
+                assert_eq!(before.departure(), "06:04");
+                assert_eq!(after.departure(), "06:14");
+            }
+            change => panic!("unexpected change {:?}", change),
+        }
+        assert!(matches!(&changes[1], Change::Removed(journey) if journey.trains() == "R11"));
+        assert!(matches!(&changes[2], Change::Added(journey) if journey.trains() == "R2"));
+    }
+
+    #[test]
+    fn test_build_diff_table() {
+        let table = build_diff_table(&changes());
+        assert_eq!(table[0][2].get_content(), "06:04 → 08:22");
+        assert_eq!(table[0][3].get_content(), "06:14 → 08:22");
+        assert_eq!(table[2][0].get_content(), "➕ Added");
+    }
+
+    #[test]
+    fn test_diff_json() {
+        let date = fixture_date();
+        let json = diff_json(&changes(), date, date);
+        assert_eq!(json[0]["change"], "retimed");
+        assert_eq!(json[0]["after"]["departure_at"], "2024-03-07T06:14");
+        assert!(json[1]["after"].is_null());
+    }
+}
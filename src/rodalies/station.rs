@@ -1,7 +1,8 @@
 use clap::ArgMatches;
 use prettytable::{Cell, Row};
 use scraper::Selector;
-use std::error::Error;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, error::Error};
 use surf::Client;
 
 use crate::{
@@ -10,7 +11,7 @@ use crate::{
 };
 
 /// The Station information struct
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Station {
     /// The internal ID of the station name, that is provided by the rodalies site. It is the value used when submitting a search.
     pub id: String,
@@ -105,3 +106,211 @@ pub async fn search_station_input(
         Err(format!("🚨 No stations found with text '{}' in it, please try searching something else, and if the problem persists open an issue...", search).into())
     }
 }
+
+/// Tells whether the given value is a station ID rather than a station name.
+pub fn is_station_id(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Finds the station matching the given ID or name, which must be the exact name or the only one containing it.
+pub fn resolve_station(stations_list: &[Station], value: &str) -> Result<Station, Box<dyn Error>> {
+    if is_station_id(value) {
+        return match stations_list.iter().find(|station| station.id == value) {
+            Some(station) => Ok(station.clone()),
+            None => Err(format!("🚨 No station found with ID '{}'", value).into()),
+        };
+    }
+
+    let search = value.to_lowercase();
+    if let Some(station) = stations_list
+        .iter()
+        .find(|station| station.name.to_lowercase() == search)
+    {
+        return Ok(station.clone());
+    }
+
+    let found: Vec<&Station> = stations_list
+        .iter()
+        .filter(|station| station.name.to_lowercase().contains(&search))
+        .collect();
+    match found.as_slice() {
+        [station] => Ok((*station).clone()),
+        [] => Err(format!(
+            "🚨 No stations found with text '{}' in it, please try searching something else",
+            value
+        )
+        .into()),
+        _ => Err(format!(
+            "🚨 Several stations found with text '{}' in it, please be more specific: {}",
+            value,
+            found
+                .iter()
+                .map(|station| station.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into()),
+    }
+}
+
+/// Resolves the given station IDs or names, fetching the stations list only if any is given by name.
+pub async fn resolve_stations(
+    client: &Client,
+    values: &[String],
+) -> Result<Vec<Station>, Box<dyn Error>> {
+    let stations_list = if values.iter().all(|value| is_station_id(value)) {
+        Vec::new()
+    } else {
+        get_stations_list(client).await?
+    };
+
+    values
+        .iter()
+        .map(|value| {
+            if is_station_id(value) {
+                Ok(Station {
+                    id: value.to_string(),
+                    name: value.to_string(),
+                })
+            } else {
+                resolve_station(&stations_list, value)
+            }
+        })
+        .collect()
+}
+
+/// Lowercases and removes the accents of the given text, so that it can be compared loosely.
+fn normalize(text: &str) -> Vec<char> {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'á' => 'a',
+            'è' | 'é' => 'e',
+            'ì' | 'í' | 'ï' => 'i',
+            'ò' | 'ó' => 'o',
+            'ù' | 'ú' | 'ü' => 'u',
+            'ç' => 'c',
+            'ñ' => 'n',
+            '·' => '.',
+            other => other,
+        })
+        .collect()
+}
+
+/// Scores how well the `pattern` fuzzy matches the `name`, higher being better.
+///
+/// All the pattern characters must be found in order on the name, otherwise it is `None`.
+/// Consecutive matches, matches at the start of a word and exact substrings score higher.
+pub fn fuzzy_score(pattern: &str, name: &str) -> Option<usize> {
+    let pattern: Vec<char> = normalize(pattern)
+        .into_iter()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let name = normalize(name);
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut position = 0;
+
+    for p in pattern.iter() {
+        let found = name[position..].iter().position(|c| c == p)? + position;
+        score += 1;
+        if found > 0 && last_match == Some(found - 1) {
+            score += 2;
+        }
+        if found == 0 || !name[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(found);
+        position = found + 1;
+    }
+
+    if name
+        .windows(pattern.len().max(1))
+        .any(|window| window == pattern.as_slice())
+    {
+        score += 10;
+    }
+
+    Some(score)
+}
+
+/// Returns the stations fuzzy matching the `pattern`, best matches first.
+pub fn fuzzy_search_stations(stations_list: &[Station], pattern: &str) -> Vec<Station> {
+    let mut scored: Vec<(usize, &Station)> = stations_list
+        .iter()
+        .filter_map(|station| fuzzy_score(pattern, &station.name).map(|score| (score, station)))
+        .collect();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored
+        .into_iter()
+        .map(|(_, station)| station.clone())
+        .collect()
+}
+
+/// Builds a station for the tests.
+#[cfg(test)]
+pub fn station(id: &str, name: &str) -> Station {
+    Station {
+        id: id.to_string(),
+        name: name.to_string(),
+    }
+}
+
+/// Builds a trip for the tests, naming its stations after their ids.
+#[cfg(test)]
+pub fn trip(from: &str, to: &str) -> crate::rodalies::trip::Trip {
+    crate::rodalies::trip::Trip {
+        from: station(from, from),
+        to: station(to, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_station_by_id_or_name() {
+        let stations_list = vec![
+            station("79202", "Sils"),
+            station("71801", "Barcelona-Sants"),
+            station("71701", "Sitges"),
+        ];
+        assert_eq!(
+            resolve_station(&stations_list, "71701").unwrap().name,
+            "Sitges"
+        );
+        assert_eq!(
+            resolve_station(&stations_list, "sants").unwrap().id,
+            "71801"
+        );
+        assert_eq!(resolve_station(&stations_list, "SILS").unwrap().id, "79202");
+        assert!(resolve_station(&stations_list, "si").is_err());
+        assert!(resolve_station(&stations_list, "12345").is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_score_requires_all_characters_in_order() {
+        assert!(fuzzy_score("snts", "Barcelona-Sants").is_some());
+        assert!(fuzzy_score("stns", "Sils").is_none());
+        assert!(fuzzy_score("gracia", "Barcelona-Passeig de Gràcia").is_some());
+    }
+
+    #[test]
+    fn test_fuzzy_search_stations_ranks_best_matches_first() {
+        let stations_list = vec![
+            station("79202", "Sils"),
+            station("71801", "Barcelona-Sants"),
+            station("71701", "Sitges"),
+        ];
+        let found = fuzzy_search_stations(&stations_list, "si");
+        assert_eq!(
+            found.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            ["79202", "71701"]
+        );
+        assert_eq!(
+            fuzzy_search_stations(&stations_list, "sants")[0].id,
+            "71801"
+        );
+    }
+}
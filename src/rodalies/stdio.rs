@@ -0,0 +1,170 @@
+use serde_json::{json, Value};
+use std::{collections::HashMap, error::Error, time::Duration};
+use surf::Client;
+use tokio::io::{stdin, stdout, AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::rodalies::api::{Api, ApiRequest};
+
+/// The JSON-RPC error code of a request that is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The JSON-RPC error code of a JSON value that is not a valid request.
+const INVALID_REQUEST: i64 = -32600;
+/// The JSON-RPC error code of an unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The JSON-RPC error code of wrong method parameters.
+const INVALID_PARAMS: i64 = -32602;
+/// The JSON-RPC error code of a failure answering the request, e.g. the rodalies site being down.
+const SERVER_ERROR: i64 = -32000;
+
+/// Answers JSON-RPC requests read line by line from stdin, writing one response line per request to stdout.
+///
+/// The client, the stations list and the requested timetables are kept between calls, until stdin is closed.
+pub async fn serve_stdio(client: &Client, ttl: Duration) -> Result<(), Box<dyn Error>> {
+    let api = Api::new(client.clone(), ttl);
+    // warm the stations list up, a failure is reported on the first call needing it
+    let _ = api.stations_list().await;
+
+    let mut lines = BufReader::new(stdin()).lines();
+    let mut output = stdout();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = answer_line(&api, &line).await {
+            output
+                .write_all(format!("{}\n", response).as_bytes())
+                .await?;
+            output.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Answers a JSON-RPC request line, there is no response for notifications (requests without `id`).
+pub async fn answer_line(api: &Api, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(error) => return Some(error_response(Value::Null, PARSE_ERROR, &error.to_string())),
+    };
+    let id = request.get("id").cloned();
+    let response_id = id.clone().unwrap_or(Value::Null);
+
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            return Some(error_response(
+                response_id,
+                INVALID_REQUEST,
+                "🚨 Please, send JSON-RPC 2.0 requests with a method",
+            ))
+        }
+    };
+    let name = match method {
+        "searchStations" => "stations",
+        "getTimetable" => "timetable",
+        "nextDepartures" => "next",
+        _ => {
+            return id.map(|id| {
+                error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("🚨 Unknown method '{}'", method),
+                )
+            })
+        }
+    };
+
+    let result = match rpc_params(request.get("params")) {
+        Ok(params) => match ApiRequest::parse(name, &params) {
+            Ok(api_request) => api
+                .answer(api_request)
+                .await
+                .map_err(|error| (SERVER_ERROR, error.to_string())),
+            Err(error) => Err((INVALID_PARAMS, error.to_string())),
+        },
+        Err(error) => Err((INVALID_PARAMS, error.to_string())),
+    };
+
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+/// Turns the named JSON-RPC params into the API parameters, `query` being an alias of `q`.
+fn rpc_params(params: Option<&Value>) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let params = match params {
+        None | Some(Value::Null) => return Ok(HashMap::new()),
+        Some(Value::Object(params)) => params,
+        Some(..) => return Err("🚨 Please, send the params as an object".into()),
+    };
+
+    params
+        .iter()
+        .map(|(key, value)| {
+            let key = if key == "query" { "q" } else { key.as_str() };
+            let value = match value {
+                Value::String(value) => value.to_string(),
+                Value::Number(value) => value.to_string(),
+                _ => return Err(format!("🚨 Please, send '{}' as a string or number", key).into()),
+            };
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Builds a JSON-RPC error response.
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::client::init_client;
+
+    fn answer(line: &str) -> Option<Value> {
+        let api = Api::new(init_client(), Duration::from_secs(60));
+        tokio_test::block_on(answer_line(&api, line))
+    }
+
+    #[test]
+    fn test_answer_line_errors() {
+        let response = answer("{not json").unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = answer(r#"{"jsonrpc":"2.0","id":1}"#).unwrap();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let response = answer(r#"{"jsonrpc":"2.0","id":2,"method":"bookTrain"}"#).unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 2);
+
+        let response = answer(
+            r#"{"jsonrpc":"2.0","id":"a","method":"nextDepartures","params":{"from":79300,"count":2}}"#,
+        )
+        .unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        assert_eq!(
+            response["error"]["message"],
+            "🚨 Please, specify the 'to' parameter"
+        );
+        assert_eq!(response["id"], "a");
+
+        let response =
+            answer(r#"{"jsonrpc":"2.0","id":3,"method":"getTimetable","params":[1,2]}"#).unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_answer_line_ignores_notifications() {
+        assert!(answer(r#"{"jsonrpc":"2.0","method":"bookTrain"}"#).is_none());
+        assert!(answer(r#"{"jsonrpc":"2.0","method":"nextDepartures","params":{}}"#).is_none());
+    }
+}
@@ -1,19 +1,125 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use clap::ArgMatches;
-use prettytable::{Cell, Row};
+use futures::{stream, StreamExt};
+use prettytable::{color, Attr, Cell, Row, Table};
 use scraper::{ElementRef, Html, Selector};
-use std::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{error::Error, fmt, fs::File, time::Duration};
 use surf::Client;
 
 use crate::{
-    config::cli::{init_results_table, parse_date, parse_trip},
-    rodalies::client::get_timetable_page,
+    config::cli::{format_date, has_date, init_results_table, parse_date, parse_trip},
+    rodalies::{
+        arrive::{parse_arrive_by, search_arrive_by},
+        client::get_timetable_page,
+        filter::{apply_filter, parse_filter},
+        history::{save_to_history, DateMode, HistoryEntry},
+        line::{line_cell, Line},
+        merge::{check_merged_args, parse_trips, search_merged},
+        notify::{parse_notify, remind_departure},
+        risk::{
+            assess_risks, build_risks_warning, mark_risks, parse_tight_transfer, ConnectionRisk,
+            TIGHT_TRANSFER,
+        },
+        station::Station,
+        trip::{parse_trip_input, Trip},
+        via::{parse_via, search_via},
+        watch::{parse_departure, parse_watch, watch_departures, WatchMode},
+    },
 };
 
 /// Displays a table with the found train timetable.
 pub async fn search_timetable(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let (origins, destinations) = parse_trips(args)?;
+    if origins.len() > 1 || destinations.len() > 1 {
+        check_merged_args(args)?;
+    }
     let (from, to) = parse_trip(args)?;
     let date = parse_date(args)?;
-    search_timetable_input(client, from, to, date).await
+    let filter = parse_filter(args)?;
+
+    let watch = parse_watch(args)?;
+    let notify = parse_notify(args)?;
+    if watch.is_some() || notify.is_some() {
+        let trip = parse_trip_input(client, args).await?;
+        let mode = WatchMode::Timetable {
+            date: NaiveDate::parse_from_str(&date, "%d/%m/%Y")?,
+            hour: "00".to_string(),
+        };
+        return match (watch, notify) {
+            (Some(config), _) => watch_departures(client, &trip, mode, config, &filter).await,
+            (None, Some(notify)) => {
+                let departure = parse_departure(args)?;
+                remind_departure(client, &trip, mode, departure, notify, &filter).await
+            }
+            (None, None) => Ok(()),
+        };
+    }
+
+    // `today` and `tomorrow` keep their meaning when the search is repeated
+    let date_mode = match args.get_one::<String>("date").map(|date| date.as_str()) {
+        Some("today") => DateMode::Today,
+        Some("tomorrow") => DateMode::Tomorrow,
+        _ if has_date(args) => DateMode::Date(date),
+        _ => DateMode::Today,
+    };
+    let arrive_by = parse_arrive_by(args)?;
+    let station = |id: String| Station {
+        name: id.clone(),
+        id,
+    };
+    let entry = HistoryEntry {
+        filter,
+        sort: args.get_one::<String>("sort").cloned(),
+        tight_transfer: parse_tight_transfer(args)?,
+        via: parse_via(args)?,
+        arrive_by: arrive_by.map(|(arrive_by, _)| arrive_by.format("%H:%M").to_string()),
+        buffer: arrive_by.map_or(0, |(_, buffer)| buffer),
+        more_from: origins[1..].to_vec(),
+        more_to: destinations[1..].to_vec(),
+        ..HistoryEntry::new(station(from), station(to), date_mode, "00".to_string())
+    };
+    run_search(client, entry).await
+}
+
+/// Runs the search of the history entry, of several origins or destinations, through via stations, arriving by a time or a plain one.
+///
+/// The search is saved on the history once completed.
+pub async fn run_search(client: &Client, mut entry: HistoryEntry) -> Result<(), Box<dyn Error>> {
+    let date = entry.date.date()?;
+    let (from, to) = (entry.from.id.clone(), entry.to.id.clone());
+    let sort = entry.sort.as_deref();
+
+    if entry.is_merged() {
+        let mut origins = vec![from];
+        origins.extend(entry.more_from.iter().cloned());
+        let mut destinations = vec![to];
+        destinations.extend(entry.more_to.iter().cloned());
+        search_merged(client, origins, destinations, date, &entry.filter, sort).await?;
+        save_to_history(entry);
+        return Ok(());
+    }
+
+    let mut timetable = if !entry.via.is_empty() {
+        search_via(client, from, to, entry.via.clone(), date, &entry.filter).await?
+    } else if let Some(deadline) = entry.deadline()? {
+        search_arrive_by(client, from, to, date, deadline, &entry.filter).await?
+    } else {
+        let hour = entry.hour.clone();
+        let mut timetable = get_timetable(client, from, to, format_date(&date), hour).await?;
+        apply_filter(&mut timetable, &entry.filter)?;
+        timetable
+    };
+    present_timetable(&mut timetable, entry.tight_transfer, sort);
+
+    // station names are only known from the found journeys, otherwise the given values are kept as names
+    if let Some(journey) = timetable.journeys.first() {
+        entry.from.name = journey.legs[0].origin.clone();
+        entry.to.name = journey.legs[journey.transfers()].destination.clone();
+    }
+    save_to_history(entry);
+    Ok(())
 }
 
 // Convenience function to avoid unwrap()ing all the time
@@ -62,33 +168,232 @@ impl VecParser for ElementRef<'_> {
     }
 }
 
-pub async fn search_timetable_input(
+/// A single train ride of a journey, from boarding to getting off.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Leg {
+    /// The train line name, e.g. `R11`.
+    pub train: String,
+    /// The station name where the train is boarded.
+    pub origin: String,
+    /// The departure time (`HH:MM`) from the origin station.
+    pub departure: String,
+    /// The station name where the train is left.
+    pub destination: String,
+    /// The arrival time (`HH:MM`) at the destination station.
+    pub arrival: String,
+}
+
+impl Leg {
+    /// The train line of the leg, if its name is a known one.
+    pub fn line(&self) -> Option<Line> {
+        self.train.parse().ok()
+    }
+}
+
+/// A journey as listed on the timetable results page, made of one leg per train taken.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Journey {
+    /// The total duration text (`HH:MM`) of the journey.
+    pub duration: String,
+    /// The legs of the journey, there is always at least one.
+    pub legs: Vec<Leg>,
+    /// The waiting time text (e.g. `5 min`) at each transfer, one less than the legs.
+    pub waits: Vec<String>,
+    /// The transfers that might be missed.
+    #[serde(default)]
+    pub risks: Vec<ConnectionRisk>,
+}
+
+impl Journey {
+    /// The departure time (`HH:MM`) of the journey.
+    pub fn departure(&self) -> &str {
+        &self.legs[0].departure
+    }
+
+    /// The arrival time (`HH:MM`) of the journey.
+    pub fn arrival(&self) -> &str {
+        &self.legs[self.legs.len() - 1].arrival
+    }
+
+    /// The total duration of the journey in minutes, if its text is well formed.
+    pub fn duration_minutes(&self) -> Option<i64> {
+        let (hours, minutes) = self.duration.split_once(':')?;
+        Some(hours.trim().parse::<i64>().ok()? * 60 + minutes.trim().parse::<i64>().ok()?)
+    }
+
+    /// The departure and arrival times of every leg, in order, as dates and times from the searched date.
+    ///
+    /// Times roll over to the next day whenever one precedes the previous one, as when the journey goes past midnight.
+    pub fn schedule(&self, date: NaiveDate) -> Option<Vec<NaiveDateTime>> {
+        let times: Vec<&str> = self
+            .legs
+            .iter()
+            .flat_map(|leg| [leg.departure.as_str(), leg.arrival.as_str()])
+            .collect();
+        roll_times(date, &times)
+    }
+
+    /// The departure date and time of the journey, on the searched date.
+    pub fn departure_at(&self, date: NaiveDate) -> Option<NaiveDateTime> {
+        Some(date.and_time(parse_time(self.departure())?))
+    }
+
+    /// The arrival date and time of the journey, the day after the searched date if it goes past midnight.
+    pub fn arrival_at(&self, date: NaiveDate) -> Option<NaiveDateTime> {
+        self.schedule(date)?.last().copied()
+    }
+
+    /// The minutes from the start of the day until the journey arrives, past midnight if it arrives the day after.
+    pub fn arrival_minutes(&self) -> Option<i64> {
+        let date = NaiveDate::default();
+        Some((self.arrival_at(date)? - date.and_time(NaiveTime::MIN)).num_minutes())
+    }
+
+    /// The departure and arrival times of every leg, in order, marked with `+N` when they are N days after the departure.
+    pub fn schedule_labels(&self) -> Vec<String> {
+        let date = NaiveDate::default();
+        match self.schedule(date) {
+            Some(schedule) => schedule
+                .iter()
+                .map(|time| format_time(*time, date))
+                .collect(),
+            None => self
+                .legs
+                .iter()
+                .flat_map(|leg| [leg.departure.clone(), leg.arrival.clone()])
+                .collect(),
+        }
+    }
+
+    /// The arrival time of the journey, marked with `+1` when it arrives the day after.
+    pub fn arrival_label(&self) -> String {
+        self.schedule_labels().pop().unwrap_or_default()
+    }
+
+    /// The waiting minutes at each transfer, `None` for the ones whose text cannot be read.
+    pub fn wait_minutes(&self) -> Vec<Option<i64>> {
+        self.waits.iter().map(|wait| parse_wait(wait)).collect()
+    }
+
+    /// The number of transfers of the journey.
+    pub fn transfers(&self) -> usize {
+        self.legs.len() - 1
+    }
+
+    /// The train lines taken on the journey, in order.
+    pub fn trains(&self) -> String {
+        self.legs
+            .iter()
+            .map(|leg| leg.train.as_str())
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+}
+
+/// The timetable results of a search.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timetable {
+    /// The journeys found, in the order provided by the rodalies site.
+    pub journeys: Vec<Journey>,
+    /// The maximum number of transfers of the listed journeys.
+    pub transfers: usize,
+    /// The expected minimum temperature at destination.
+    pub min_temp: Option<String>,
+    /// The expected maximum temperature at destination.
+    pub max_temp: Option<String>,
+}
+
+/// Why the timetable of a trip could not be got.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimetableError {
+    /// The rodalies site reported there are no trains, as for a trip not running on the date, with its messages.
+    NoTrains(String),
+    /// The request failed, or the results page could not be read.
+    Failed(String),
+}
+
+impl fmt::Display for TimetableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimetableError::NoTrains(errors) => write!(
+                f,
+                "⛔ Errors found and reported from Rodalies site: {}. 🚨 Please, make sure you provided right flags and values",
+                errors
+            ),
+            TimetableError::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for TimetableError {}
+
+/// Requests and parses the timetable of the given trip.
+pub async fn get_timetable(
     client: &Client,
     from: String,
     to: String,
     date: String,
-) -> Result<(), Box<dyn Error>> {
-    let mut results_table = init_results_table();
+    hour: String,
+) -> Result<Timetable, TimetableError> {
+    let parsed_html = get_timetable_page(client, from, to, date, hour)
+        .await
+        .map_err(|error| TimetableError::Failed(error.to_string()))?;
+    parse_timetable(&parsed_html)
+}
 
-    let parsed_html = get_timetable_page(client, from, to, date).await?;
+/// Requests the timetables of many trips on the same date, from the same hour, keeping their order.
+///
+/// To be polite with the rodalies site, at most `concurrency` requests are sent at a time, each one after waiting `delay`.
+pub async fn get_timetables(
+    client: &Client,
+    trips: &[Trip],
+    date: NaiveDate,
+    hour: &str,
+    concurrency: usize,
+    delay: Duration,
+) -> Vec<Result<Timetable, TimetableError>> {
+    let queries: Vec<(Trip, NaiveDate)> = trips.iter().map(|trip| (trip.clone(), date)).collect();
+    get_dated_timetables(client, &queries, hour, concurrency, delay).await
+}
 
-    // check, show and fail if displayed errors
-    let selector_errors = &Selector::parse(r#".error_contingut > p"#)?;
-    let errors: Vec<&str> = parsed_html
-        .select(selector_errors)
-        .flat_map(|el| el.text())
-        .collect();
+/// Requests the timetables of many trips, each one on its own date, from the same hour, keeping their order.
+///
+/// Requests are sent as politely as in `get_timetables`.
+pub async fn get_dated_timetables(
+    client: &Client,
+    queries: &[(Trip, NaiveDate)],
+    hour: &str,
+    concurrency: usize,
+    delay: Duration,
+) -> Vec<Result<Timetable, TimetableError>> {
+    stream::iter(queries)
+        .map(|(trip, date)| async move {
+            tokio::time::sleep(delay).await;
+            get_timetable(
+                client,
+                trip.from.id.clone(),
+                trip.to.id.clone(),
+                format_date(date),
+                hour.to_string(),
+            )
+            .await
+        })
+        .buffered(concurrency)
+        .collect()
+        .await
+}
+
+/// Parses the timetable results page, failing if the rodalies site reported any error.
+pub fn parse_timetable(parsed_html: &Html) -> Result<Timetable, TimetableError> {
+    // check and fail if displayed errors
+    let errors = parsed_html.texts_parser(make_selector(r#".error_contingut > p"#));
     if !errors.is_empty() {
-        println!("⛔ Errors found and reported from Rodalies site:");
-        for (pos, e) in errors.iter().enumerate() {
-            println!("💩 {}: {:?}", pos + 1, e);
-        }
-        return Err("🚨 Please, make sure you provided right flags and values".into());
+        return Err(TimetableError::NoTrains(errors.join(" ")));
     }
 
     let resum_selector = make_selector(r#"div.resum > div.col-sm-12 > div.taula.d60 > div.cel"#);
-    let total_stations = parsed_html.select(&resum_selector);
-    // println!("#estacions: {:?}", &total_stations.count());
+    let total = parsed_html.select(&resum_selector).count();
+
     let min_temp_selector = make_selector(
         r#"div.resum > div.col-sm-12 > div.taula.d40 > div.cel > div.info > span.t-min"#,
     );
@@ -99,33 +404,10 @@ pub async fn search_timetable_input(
     );
     let max_temp = parsed_html.texts_parser(max_temp_selector);
 
-    // Create timetable's first row
-    let mut title_cells: Vec<Cell> = vec![
-        Cell::new("Duration"),
-        Cell::new("Train"),
-        Cell::new("Station"),
-        Cell::new("Start"),
-    ];
-    let total = total_stations.count();
-
-    println!("📆 Listing timetable with {} transfers", total - 2);
-
-    for _ in 2..total {
-        title_cells.push(Cell::new("Stop"));
-        title_cells.push(Cell::new("Transfer"));
-        title_cells.push(Cell::new("Wait"));
-        title_cells.push(Cell::new("Train"));
-        title_cells.push(Cell::new("Start"));
-    }
-    title_cells.push(Cell::new("End"));
-    title_cells.push(Cell::new("Station"));
-    results_table.set_titles(Row::new(title_cells.clone()));
-
-    let mut different_lengths = false;
+    let mut journeys: Vec<Journey> = Vec::new();
 
     let rows_selector = make_selector(r#"#acordio_resultats > div.panel.panel-default"#);
-    let rows = parsed_html.select(&rows_selector);
-    for row in rows {
+    for row in parsed_html.select(&rows_selector) {
         let durada_selector: Selector = make_selector(r#"div.resultats-fila > div.durada"#);
         let durada = row.texts_parser(durada_selector);
 
@@ -151,22 +433,190 @@ pub async fn search_timetable_input(
         let estacions_selector: Selector = make_selector(r#"div.estacio > h3.timeline-title"#);
         let estacions = row.texts_parser(estacions_selector);
 
+        if durada.is_empty()
+            || hora_arribades.is_empty()
+            || hora_sortides.len() <= hora_transbords.len()
+            || tren_sortides.len() <= hora_transbords.len()
+            || estacions.len() < hora_transbords.len() + 2
+            || durada_transbords.len() < hora_transbords.len()
+        {
+            return Err(TimetableError::Failed(
+                "🚨 Unexpected timetable format found, please open an issue...".to_string(),
+            ));
+        }
+
+        let legs: Vec<Leg> = (0..=hora_transbords.len())
+            .map(|leg| Leg {
+                train: tren_sortides[leg].clone(),
+                origin: estacions[leg].clone(),
+                departure: hora_sortides[leg].clone(),
+                destination: estacions[leg + 1].clone(),
+                arrival: match hora_transbords.get(leg) {
+                    Some(stop) => stop.clone(),
+                    None => hora_arribades[0].clone(),
+                },
+            })
+            .collect();
+
+        let mut journey = Journey {
+            duration: durada[0].clone(),
+            legs,
+            waits: durada_transbords[..hora_transbords.len()].to_vec(),
+            risks: Vec::new(),
+        };
+        journey.risks = assess_risks(&journey, TIGHT_TRANSFER);
+        journeys.push(journey);
+    }
+
+    Ok(Timetable {
+        journeys,
+        transfers: total.saturating_sub(2),
+        min_temp: min_temp.first().cloned(),
+        max_temp: max_temp.first().cloned(),
+    })
+}
+
+/// Parses a `HH:MM` time as displayed on the timetable results page.
+pub fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+/// Parses a waiting time text, such as `5 min` or `1 h 5 min`, into minutes.
+pub fn parse_wait(wait: &str) -> Option<i64> {
+    let mut minutes = 0;
+    let mut tokens = wait.split_whitespace().peekable();
+    tokens.peek()?;
+    while let Some(value) = tokens.next() {
+        let value = value.parse::<i64>().ok()?;
+        match tokens.next()? {
+            "h" => minutes += value * 60,
+            "min" => minutes += value,
+            _ => return None,
+        }
+    }
+    Some(minutes)
+}
+
+/// Turns the `HH:MM` times, in order, into dates and times from the given date.
+///
+/// A time preceding the previous one is taken as the day after it, so that journeys can go past midnight.
+pub fn roll_times(date: NaiveDate, times: &[&str]) -> Option<Vec<NaiveDateTime>> {
+    let mut rolled: Vec<NaiveDateTime> = Vec::with_capacity(times.len());
+    let mut day = date;
+    for time in times {
+        let time = parse_time(time)?;
+        if rolled
+            .last()
+            .is_some_and(|previous| day.and_time(time) < *previous)
+        {
+            day = day.succ_opt()?;
+        }
+        rolled.push(day.and_time(time));
+    }
+    Some(rolled)
+}
+
+/// Formats the date and time as `HH:MM`, followed by `+N` when it is N days after the given date.
+pub fn format_time(time: NaiveDateTime, date: NaiveDate) -> String {
+    let days = (time.date() - date).num_days();
+    if days > 0 {
+        format!("{} +{}", time.format("%H:%M"), days)
+    } else {
+        time.format("%H:%M").to_string()
+    }
+}
+
+/// Returns the journey as JSON, with the departure and arrival dates and times of the journey and of every leg.
+pub fn journey_json(journey: &Journey, date: NaiveDate) -> Value {
+    let format = |time: &NaiveDateTime| time.format("%Y-%m-%dT%H:%M").to_string();
+    let mut value = json!(journey);
+    if let Some(schedule) = journey.schedule(date) {
+        value["departure_at"] = json!(schedule.first().map(format));
+        value["arrival_at"] = json!(schedule.last().map(format));
+        if let Some(legs) = value["legs"].as_array_mut() {
+            for (leg, times) in legs.iter_mut().zip(schedule.chunks(2)) {
+                leg["departure_at"] = json!(format(&times[0]));
+                leg["arrival_at"] = json!(format(&times[1]));
+            }
+        }
+    }
+    value
+}
+
+/// Returns the timetable as JSON, with the dates and times of its journeys from the searched date.
+pub fn timetable_json(timetable: &Timetable, date: NaiveDate) -> Value {
+    let mut value = json!(timetable);
+    value["journeys"] = timetable
+        .journeys
+        .iter()
+        .map(|journey| journey_json(journey, date))
+        .collect();
+    value
+}
+
+/// Returns the index of the first journey departing at or after `now`.
+pub fn next_departure_index(journeys: &[Journey], now: NaiveTime) -> Option<usize> {
+    journeys.iter().position(|journey| {
+        parse_time(journey.departure())
+            .map(|departure| departure >= now)
+            .unwrap_or(false)
+    })
+}
+
+/// Builds the table with the found train timetable, with as many columns as transfers.
+///
+/// When `marked`, the waits of the transfers that might be missed are highlighted.
+pub fn build_timetable_table(timetable: &Timetable, marked: bool) -> Table {
+    let mut results_table = init_results_table();
+    let total = timetable.transfers;
+
+    // Create timetable's first row
+    let mut title_cells: Vec<Cell> = vec![
+        Cell::new("Duration"),
+        Cell::new("Train"),
+        Cell::new("Station"),
+        Cell::new("Start"),
+    ];
+
+    for _ in 0..total {
+        title_cells.push(Cell::new("Stop"));
+        title_cells.push(Cell::new("Transfer"));
+        title_cells.push(Cell::new("Wait"));
+        title_cells.push(Cell::new("Train"));
+        title_cells.push(Cell::new("Start"));
+    }
+    title_cells.push(Cell::new("End"));
+    title_cells.push(Cell::new("Station"));
+    results_table.set_titles(Row::new(title_cells));
+
+    for journey in timetable.journeys.iter() {
+        let first = &journey.legs[0];
+        let last = &journey.legs[journey.legs.len() - 1];
+        let labels = journey.schedule_labels();
+
         let mut row_cells: Vec<Cell> = vec![
-            Cell::new(durada[0].as_str()),
-            Cell::new(tren_sortides[0].as_str()),
-            Cell::new(estacions[0].as_str()),
-            Cell::new(hora_sortides[0].as_str()),
+            Cell::new(journey.duration.as_str()),
+            line_cell(first.train.as_str()),
+            Cell::new(first.origin.as_str()),
+            Cell::new(&labels[0]),
         ];
-        for tx in 0..hora_transbords.len() {
-            row_cells.push(Cell::new(hora_transbords[tx].as_str()));
-            row_cells.push(Cell::new(estacions[tx + 1].as_str()));
-            row_cells.push(Cell::new(durada_transbords[tx].as_str()));
-            row_cells.push(Cell::new(tren_sortides[tx + 1].as_str()));
-            row_cells.push(Cell::new(hora_sortides[tx + 1].as_str()));
+        for tx in 0..journey.transfers() {
+            row_cells.push(Cell::new(&labels[tx * 2 + 1]));
+            row_cells.push(Cell::new(journey.legs[tx].destination.as_str()));
+            row_cells.push(
+                if marked && journey.risks.iter().any(|risk| risk.transfer == tx) {
+                    Cell::new(&format!("⚠ {}", journey.waits[tx]))
+                        .with_style(Attr::Bold)
+                        .with_style(Attr::ForegroundColor(color::YELLOW))
+                } else {
+                    Cell::new(journey.waits[tx].as_str())
+                },
+            );
+            row_cells.push(line_cell(journey.legs[tx + 1].train.as_str()));
+            row_cells.push(Cell::new(&labels[tx * 2 + 2]));
         }
-        if hora_transbords.len() < total - 2 {
-            different_lengths = true;
-            for _ in 0..(total - 2 - hora_transbords.len()) {
+        if journey.transfers() < total {
+            for _ in 0..(total - journey.transfers()) {
                 row_cells.push(Cell::new(""));
                 row_cells.push(Cell::new(""));
                 row_cells.push(Cell::new(""));
@@ -174,20 +624,345 @@ pub async fn search_timetable_input(
                 row_cells.push(Cell::new(""));
             }
         }
-        row_cells.push(Cell::new(hora_arribades[0].as_str()));
-        row_cells.push(Cell::new(estacions[estacions.len() - 1].as_str()));
-        results_table.add_row(Row::new(row_cells.clone()));
+        row_cells.push(Cell::new(&labels[labels.len() - 1]));
+        row_cells.push(Cell::new(last.destination.as_str()));
+        results_table.add_row(Row::new(row_cells));
+    }
+
+    results_table
+}
+
+/// Returns the key to sort the journey by the given field: `departure`, `arrival`, `duration` or `transfers`.
+///
+/// The journeys whose times cannot be read get the greatest key, to go last.
+pub fn sort_key(journey: &Journey, sort: &str) -> i64 {
+    let last = |value: Option<i64>| value.unwrap_or(i64::MAX);
+    match sort {
+        "arrival" => last(journey.arrival_minutes()),
+        "duration" => last(journey.duration_minutes()),
+        "transfers" => journey.transfers() as i64,
+        _ => last(
+            parse_time(journey.departure())
+                .map(|departure| i64::from(departure.num_seconds_from_midnight())),
+        ),
+    }
+}
+
+/// Sorts the journeys by the given field: `departure`, `arrival`, `duration` or `transfers`.
+///
+/// Journeys keep the site order on ties, and the ones whose times cannot be read go last.
+pub fn sort_journeys(journeys: &mut [Journey], sort: &str) {
+    journeys.sort_by_key(|journey| sort_key(journey, sort));
+}
+
+/// Marks the connection risks of the journeys, sorts them by the given field if any and displays them.
+pub fn present_timetable(timetable: &mut Timetable, tight: i64, sort: Option<&str>) {
+    mark_risks(timetable, tight);
+    if let Some(sort) = sort {
+        sort_journeys(&mut timetable.journeys, sort);
+    }
+    display_timetable(timetable);
+}
+
+/// Returns the summary line with the fastest, the earliest arriving and the fewest transfers journeys.
+pub fn build_summary(timetable: &Timetable) -> Option<String> {
+    let journeys = &timetable.journeys;
+    let fastest = journeys
+        .iter()
+        .filter(|journey| journey.duration_minutes().is_some())
+        .min_by_key(|journey| journey.duration_minutes())?;
+    let earliest = journeys
+        .iter()
+        .filter(|journey| journey.arrival_minutes().is_some())
+        .min_by_key(|journey| journey.arrival_minutes())?;
+    let fewest = journeys.iter().min_by_key(|journey| journey.transfers())?;
+
+    Some(format!(
+        "🏆 Fastest: {} ({}) · Earliest arrival: {} → {} · Fewest transfers: {} ({})",
+        fastest.departure(),
+        fastest.duration,
+        earliest.departure(),
+        earliest.arrival_label(),
+        fewest.departure(),
+        fewest.transfers()
+    ))
+}
+
+/// Returns the notice telling how many journeys have transfers and where the tightest one is.
+pub fn build_transfers_notice(timetable: &Timetable) -> Option<String> {
+    let journeys = &timetable.journeys;
+    let with_transfers = journeys
+        .iter()
+        .filter(|journey| journey.transfers() > 0)
+        .count();
+    if with_transfers == 0 {
+        return None;
     }
 
-    if different_lengths {
-        println!("📢 Some trips have extra transfers. You might consider getting shorter trips.");
+    let tightest = journeys
+        .iter()
+        .flat_map(|journey| {
+            journey
+                .wait_minutes()
+                .into_iter()
+                .enumerate()
+                .filter_map(move |(tx, wait)| Some((wait?, journey, tx)))
+        })
+        .min_by_key(|(wait, ..)| *wait);
+    let mut notice = format!(
+        "📢 {} of {} trips have transfers",
+        with_transfers,
+        journeys.len()
+    );
+    if let Some((wait, journey, tx)) = tightest {
+        notice.push_str(&format!(
+            ", the tightest one leaves {} min at {} ({})",
+            wait,
+            journey.legs[tx].destination,
+            journey.departure()
+        ));
     }
+    notice
+        .push_str(". Use --direct, --max-transfers, --min-transfer or --max-wait to filter them.");
+    Some(notice)
+}
 
+/// Displays a table with the found train timetable.
+pub fn display_timetable(timetable: &Timetable) {
     println!(
-        "🌡 Expected temperatures at destination between {}C and {}C",
-        min_temp[0], max_temp[0]
+        "📆 Listing timetable with {} transfers",
+        timetable.transfers
     );
-    results_table.printstd();
 
+    if let Some(notice) = build_transfers_notice(timetable) {
+        println!("{}", notice);
+    }
+    if let Some(warning) = build_risks_warning(timetable) {
+        println!("{}", warning);
+    }
+
+    if let (Some(min_temp), Some(max_temp)) = (&timetable.min_temp, &timetable.max_temp) {
+        println!(
+            "🌡 Expected temperatures at destination between {}C and {}C",
+            min_temp, max_temp
+        );
+    }
+    if timetable.journeys.len() > 1 {
+        if let Some(summary) = build_summary(timetable) {
+            println!("{}", summary);
+        }
+    }
+    build_timetable_table(timetable, true).printstd();
+}
+
+/// Exports the timetable table as CSV to the given file path.
+pub fn export_timetable(timetable: &Timetable, path: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    build_timetable_table(timetable, false)
+        .to_csv(file)?
+        .flush()?;
     Ok(())
 }
+
+/// Searches and displays the timetable of the given trip.
+pub async fn search_timetable_input(
+    client: &Client,
+    from: String,
+    to: String,
+    date: String,
+    hour: String,
+) -> Result<Timetable, Box<dyn Error>> {
+    let timetable = get_timetable(client, from, to, date, hour).await?;
+    display_timetable(&timetable);
+
+    Ok(timetable)
+}
+
+/// Returns the timetable of the test fixture, from Girona to Sitges.
+#[cfg(test)]
+pub fn fixture_timetable() -> Timetable {
+    let html =
+        crate::rodalies::client::get_html_from_file("tests/fixtures/timetable.html").unwrap();
+    parse_timetable(&html).unwrap()
+}
+
+/// Returns the date the test fixture timetable was searched for.
+#[cfg(test)]
+pub fn fixture_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, 7).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::client::get_html_from_file;
+
+    #[test]
+    fn test_sort_journeys() {
+        let mut timetable = fixture_timetable();
+        assert_eq!(timetable.journeys[1].arrival_minutes(), Some(23 * 60 + 17));
+
+        sort_journeys(&mut timetable.journeys, "duration");
+        assert_eq!(timetable.journeys[0].departure(), "21:19");
+        sort_journeys(&mut timetable.journeys, "arrival");
+        assert_eq!(timetable.journeys[0].departure(), "06:04");
+        sort_journeys(&mut timetable.journeys, "transfers");
+        assert_eq!(timetable.journeys[0].departure(), "21:19");
+        sort_journeys(&mut timetable.journeys, "departure");
+        assert_eq!(timetable.journeys[0].departure(), "06:04");
+    }
+
+    #[test]
+    fn test_build_summary() {
+        assert_eq!(
+            build_summary(&fixture_timetable()).unwrap(),
+            "🏆 Fastest: 21:19 (01:58) · Earliest arrival: 06:04 → 08:22 · Fewest transfers: 21:19 (0)"
+        );
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    /// Returns the fixture with its evening journey leaving at 23:40 and arriving after midnight.
+    fn overnight_timetable() -> Timetable {
+        let mut timetable = fixture_timetable();
+        let overnight = &mut timetable.journeys[1];
+        overnight.duration = "00:45".to_string();
+        overnight.legs[0].departure = "23:40".to_string();
+        overnight.legs[0].arrival = "00:25".to_string();
+        timetable
+    }
+
+    #[test]
+    fn test_journeys_past_midnight() {
+        let timetable = overnight_timetable();
+        let (overnight, date) = (&timetable.journeys[1], fixture_date());
+        assert_eq!(
+            overnight.schedule(date).unwrap(),
+            vec![at(7, 23, 40), at(8, 0, 25)]
+        );
+        assert_eq!(overnight.departure_at(date), Some(at(7, 23, 40)));
+        assert_eq!(overnight.arrival_at(date), Some(at(8, 0, 25)));
+        assert_eq!(overnight.arrival_minutes(), Some(24 * 60 + 25));
+        assert_eq!(overnight.arrival_label(), "00:25 +1");
+        assert_eq!(timetable.journeys[0].arrival_label(), "08:22");
+    }
+
+    #[test]
+    fn test_timetable_json_past_midnight() {
+        let json = timetable_json(&overnight_timetable(), fixture_date());
+        assert_eq!(
+            json["journeys"][0]["legs"][1]["departure_at"],
+            "2024-03-07T07:45"
+        );
+        assert_eq!(json["journeys"][1]["arrival_at"], "2024-03-08T00:25");
+        assert_eq!(json["journeys"][1]["legs"][0]["arrival"], "00:25");
+    }
+
+    #[test]
+    fn test_timetable_table_and_sort_past_midnight() {
+        let mut timetable = overnight_timetable();
+        let table = build_timetable_table(&timetable, false);
+        assert_eq!(table[1][9].get_content(), "00:25 +1");
+
+        sort_journeys(&mut timetable.journeys, "arrival");
+        assert_eq!(timetable.journeys[1].departure(), "23:40");
+    }
+
+    #[test]
+    fn test_wait_minutes_and_transfers_notice() {
+        assert_eq!(parse_wait("5 min"), Some(5));
+        assert_eq!(parse_wait("1 h 5 min"), Some(65));
+        assert_eq!(parse_wait(""), None);
+        assert_eq!(parse_wait("5 minutes"), None);
+
+        let timetable = fixture_timetable();
+        assert_eq!(timetable.journeys[0].wait_minutes(), vec![Some(5)]);
+        assert!(timetable.journeys[1].wait_minutes().is_empty());
+
+        assert_eq!(
+            build_transfers_notice(&timetable).unwrap(),
+            "📢 1 of 2 trips have transfers, the tightest one leaves 5 min at Barcelona-Sants (06:04). Use --direct, --max-transfers, --min-transfer or --max-wait to filter them."
+        );
+        let direct = Timetable {
+            journeys: vec![timetable.journeys[1].clone()],
+            ..timetable
+        };
+        assert!(build_transfers_notice(&direct).is_none());
+    }
+
+    #[test]
+    fn test_parse_timetable_with_transfers() {
+        let timetable = fixture_timetable();
+
+        assert_eq!(timetable.transfers, 1);
+        assert_eq!(timetable.min_temp.as_deref(), Some("19º"));
+        assert_eq!(timetable.journeys.len(), 2);
+
+        let journey = &timetable.journeys[0];
+        assert_eq!(journey.duration, "02:18");
+        assert_eq!(journey.transfers(), 1);
+        assert_eq!(journey.waits, vec!["5 min"]);
+        assert_eq!(
+            journey.legs[0],
+            Leg {
+                train: "R11".to_string(),
+                origin: "Girona".to_string(),
+                departure: "06:04".to_string(),
+                destination: "Barcelona-Sants".to_string(),
+                arrival: "07:40".to_string(),
+            }
+        );
+        assert_eq!(journey.legs[1].train, "R2");
+        assert_eq!(journey.arrival(), "08:22");
+        assert_eq!(journey.duration_minutes(), Some(138));
+        assert_eq!(timetable.journeys[1].legs[0].destination, "Sitges");
+    }
+
+    #[test]
+    fn test_parse_timetable_fails_on_site_errors() {
+        let html = get_html_from_file("tests/fixtures/timetable_error.html").unwrap();
+        let error = parse_timetable(&html).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("There are no trains for the selected trip."));
+        assert!(matches!(error, TimetableError::NoTrains(..)));
+    }
+
+    #[test]
+    fn test_next_departure_index() {
+        let timetable = fixture_timetable();
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+        assert_eq!(next_departure_index(&timetable.journeys, at(5, 0)), Some(0));
+        assert_eq!(next_departure_index(&timetable.journeys, at(6, 5)), Some(1));
+        assert_eq!(next_departure_index(&timetable.journeys, at(22, 0)), None);
+    }
+
+    #[test]
+    fn test_export_timetable_as_csv() {
+        let timetable = fixture_timetable();
+        let path = std::env::temp_dir().join("rodalies-cli-test-export.csv");
+
+        export_timetable(&timetable, path.to_str().unwrap()).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Duration,Train,Station,Start,Stop,Transfer,Wait,Train,Start,End,Station"
+        );
+        assert_eq!(
+            lines[1],
+            "02:18,R11,Girona,06:04,07:40,Barcelona-Sants,5 min,R2,07:45,08:22,Sitges"
+        );
+        assert_eq!(lines[2], "01:58,R11,Girona,21:19,,,,,,23:17,Sitges");
+
+        let table = build_timetable_table(&timetable, true);
+        assert_eq!(table[0][6].get_content(), "⚠ 5 min");
+    }
+}
@@ -0,0 +1,94 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error};
+use surf::Client;
+
+use crate::{
+    config::{cli::parse_trip, store},
+    rodalies::station::{get_stations_list, is_station_id, resolve_station, Station},
+};
+
+/// The name of the data file keeping the favourite trips.
+const FAVOURITES_FILE: &str = "favourites.json";
+
+/// A trip between two stations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trip {
+    /// The origin's station.
+    pub from: Station,
+    /// The destination's station.
+    pub to: Station,
+}
+
+/// Loads the favourite trips, by name.
+pub fn load_favourites() -> Result<BTreeMap<String, Trip>, Box<dyn Error>> {
+    store::load(FAVOURITES_FILE)
+}
+
+/// Saves the trip as a favourite with the given name, replacing any previous one.
+pub fn save_favourite(name: &str, trip: &Trip) -> Result<(), Box<dyn Error>> {
+    let mut favourites = load_favourites()?;
+    favourites.insert(name.to_string(), trip.clone());
+    store::save(FAVOURITES_FILE, &favourites)
+}
+
+/// Returns the favourite trip with the given name.
+pub fn get_favourite(name: &str) -> Result<Trip, Box<dyn Error>> {
+    let favourites = load_favourites()?;
+    match favourites.get(name) {
+        Some(trip) => Ok(trip.clone()),
+        None if favourites.is_empty() => Err(format!(
+            "🚨 There is no favourite trip named '{}', save one with '--save {}'",
+            name, name
+        )
+        .into()),
+        None => Err(format!(
+            "🚨 There is no favourite trip named '{}', the saved ones are: {}",
+            name,
+            favourites.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+        .into()),
+    }
+}
+
+/// Given a container of CLI args, it processes the `trip`, `from`, `to` and `save` arguments.
+///
+/// Stations given by name are looked up on the stations list, which is only requested when needed.
+/// The `trip` and `save` arguments are optional, as the top-level timetable search has none.
+pub async fn parse_trip_input(client: &Client, args: &ArgMatches) -> Result<Trip, Box<dyn Error>> {
+    if let Ok(Some(name)) = args.try_get_one::<String>("trip") {
+        return get_favourite(name);
+    }
+
+    let (from, to) = parse_trip(args)?;
+    let save = args.try_get_one::<String>("save").ok().flatten();
+
+    let trip = if is_station_id(&from) && is_station_id(&to) && save.is_none() {
+        Trip {
+            from: Station {
+                id: from.clone(),
+                name: from,
+            },
+            to: Station {
+                id: to.clone(),
+                name: to,
+            },
+        }
+    } else {
+        let stations_list = get_stations_list(client).await?;
+        Trip {
+            from: resolve_station(&stations_list, &from)?,
+            to: resolve_station(&stations_list, &to)?,
+        }
+    };
+
+    if let Some(name) = save {
+        save_favourite(name, &trip)?;
+        eprintln!(
+            "⭐ Saved the trip from '{}' to '{}' as favourite '{}'",
+            trip.from.name, trip.to.name, name
+        );
+    }
+
+    Ok(trip)
+}
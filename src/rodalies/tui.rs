@@ -0,0 +1,426 @@
+use chrono::{Datelike, Days, Local, NaiveDate, Timelike};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::error::Error;
+use surf::Client;
+
+use crate::{
+    config::cli::format_date,
+    rodalies::{
+        station::{fuzzy_search_stations, get_stations_list, Station},
+        timetable::{get_timetable, next_departure_index, Journey, Timetable},
+    },
+};
+
+/// The screens of the full-screen search, in the order they are shown.
+#[derive(Clone, Copy, PartialEq)]
+enum Screen {
+    Origin,
+    Destination,
+    Date,
+    Timetable,
+}
+
+/// What to do after handling a key press.
+enum Action {
+    None,
+    Quit,
+    Search,
+}
+
+/// The incremental station picker state.
+struct StationPicker {
+    query: String,
+    matches: Vec<Station>,
+    state: ListState,
+}
+
+impl StationPicker {
+    fn new(stations_list: &[Station]) -> StationPicker {
+        let mut picker = StationPicker {
+            query: String::new(),
+            matches: Vec::new(),
+            state: ListState::default(),
+        };
+        picker.update(stations_list);
+        picker
+    }
+
+    /// Refreshes the matching stations after the query changed.
+    fn update(&mut self, stations_list: &[Station]) {
+        self.matches = fuzzy_search_stations(stations_list, &self.query);
+        self.state.select(if self.matches.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    fn selected(&self) -> Option<Station> {
+        self.state
+            .selected()
+            .and_then(|index| self.matches.get(index))
+            .cloned()
+    }
+}
+
+/// The whole state of the full-screen search.
+struct App<'a> {
+    stations_list: &'a [Station],
+    screen: Screen,
+    picker: StationPicker,
+    origin: Option<Station>,
+    destination: Option<Station>,
+    date: NaiveDate,
+    hour: u32,
+    timetable: Option<Timetable>,
+    next_departure: Option<usize>,
+    table_state: TableState,
+    message: Option<String>,
+}
+
+/// Runs the whole timetable search on a full-screen terminal UI.
+pub async fn search_tui(client: &Client) -> Result<(), Box<dyn Error>> {
+    let stations_list = get_stations_list(client).await?;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, client, &stations_list).await;
+    ratatui::restore();
+
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: &Client,
+    stations_list: &[Station],
+) -> Result<(), Box<dyn Error>> {
+    let mut app = App {
+        stations_list,
+        screen: Screen::Origin,
+        picker: StationPicker::new(stations_list),
+        origin: None,
+        destination: None,
+        date: Local::now().date_naive(),
+        hour: 0,
+        timetable: None,
+        next_departure: None,
+        table_state: TableState::default(),
+        message: None,
+    };
+
+    loop {
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        match app.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Search => {
+                app.message = Some("⏳ Searching timetable...".to_string());
+                terminal.draw(|frame| draw(frame, &mut app))?;
+                app.search(client).await;
+            }
+        }
+    }
+}
+
+impl App<'_> {
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match self.screen {
+            Screen::Origin | Screen::Destination => self.handle_picker_key(key),
+            Screen::Date => self.handle_date_key(key),
+            Screen::Timetable => self.handle_timetable_key(key),
+        }
+    }
+
+    fn handle_picker_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Esc if self.screen == Screen::Origin => return Action::Quit,
+            KeyCode::Esc => {
+                self.screen = Screen::Origin;
+                self.picker = StationPicker::new(self.stations_list);
+            }
+            KeyCode::Char(c) => {
+                self.picker.query.push(c);
+                self.picker.update(self.stations_list);
+            }
+            KeyCode::Backspace => {
+                self.picker.query.pop();
+                self.picker.update(self.stations_list);
+            }
+            KeyCode::Down => self.picker.state.select_next(),
+            KeyCode::Up => self.picker.state.select_previous(),
+            KeyCode::Enter => {
+                if let Some(station) = self.picker.selected() {
+                    if self.screen == Screen::Origin {
+                        self.origin = Some(station);
+                        self.screen = Screen::Destination;
+                        self.picker = StationPicker::new(self.stations_list);
+                    } else {
+                        self.destination = Some(station);
+                        self.screen = Screen::Date;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn handle_date_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc => {
+                self.screen = Screen::Destination;
+                self.picker = StationPicker::new(self.stations_list);
+            }
+            KeyCode::Left => self.date = self.date - Days::new(1),
+            KeyCode::Right => self.date = self.date + Days::new(1),
+            KeyCode::Up => self.hour = (self.hour + 1) % 24,
+            KeyCode::Down => self.hour = (self.hour + 23) % 24,
+            KeyCode::Char('t') => self.date = Local::now().date_naive(),
+            KeyCode::Enter => return Action::Search,
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn handle_timetable_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc | KeyCode::Char('b') => self.screen = Screen::Date,
+            KeyCode::Down => self.table_state.select_next(),
+            KeyCode::Up => self.table_state.select_previous(),
+            KeyCode::PageDown => self.table_state.scroll_down_by(10),
+            KeyCode::PageUp => self.table_state.scroll_up_by(10),
+            KeyCode::Home => self.table_state.select_first(),
+            KeyCode::End => self.table_state.select_last(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Searches the timetable of the selected trip and moves to the timetable screen on success.
+    async fn search(&mut self, client: &Client) {
+        let (Some(origin), Some(destination)) = (&self.origin, &self.destination) else {
+            return;
+        };
+
+        match get_timetable(
+            client,
+            origin.id.clone(),
+            destination.id.clone(),
+            format_date(&self.date),
+            format!("{:02}", self.hour),
+        )
+        .await
+        {
+            Ok(timetable) => {
+                let now = Local::now();
+                self.next_departure = if self.date == now.date_naive() {
+                    next_departure_index(&timetable.journeys, now.time().with_second(0).unwrap())
+                } else {
+                    None
+                };
+                self.table_state =
+                    TableState::default().with_selected(Some(self.next_departure.unwrap_or(0)));
+                self.timetable = Some(timetable);
+                self.message = None;
+                self.screen = Screen::Timetable;
+            }
+            Err(error) => self.message = Some(error.to_string()),
+        }
+    }
+
+    fn title(&self) -> String {
+        let name = |station: &Option<Station>| {
+            station
+                .as_ref()
+                .map(|station| station.name.clone())
+                .unwrap_or_default()
+        };
+        match self.screen {
+            Screen::Origin => "🚂 Rodalies CLI · Which is your origin's station?".to_string(),
+            Screen::Destination => format!(
+                "🚂 Rodalies CLI · {} → Which is your destination's station?",
+                name(&self.origin)
+            ),
+            Screen::Date | Screen::Timetable => format!(
+                "🚂 Rodalies CLI · {} → {}",
+                name(&self.origin),
+                name(&self.destination)
+            ),
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self.screen {
+            Screen::Origin => "type to search · ↑↓ move · enter select · esc quit",
+            Screen::Destination => "type to search · ↑↓ move · enter select · esc back",
+            Screen::Date => "←→ day · ↑↓ start hour · t today · enter search · esc back · q quit",
+            Screen::Timetable => "↑↓ pgup pgdn home end scroll · esc back · q quit",
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [title_area, main_area, help_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(
+        Paragraph::new(app.title()).style(Style::default().add_modifier(Modifier::BOLD)),
+        title_area,
+    );
+
+    let help = match &app.message {
+        Some(message) => Line::styled(message.clone(), Style::default().fg(Color::Yellow)),
+        None => Line::styled(app.help(), Style::default().fg(Color::DarkGray)),
+    };
+    frame.render_widget(Paragraph::new(help), help_area);
+
+    match app.screen {
+        Screen::Origin | Screen::Destination => draw_picker(frame, main_area, app),
+        Screen::Date => draw_date(frame, main_area, app),
+        Screen::Timetable => draw_timetable(frame, main_area, app),
+    }
+}
+
+fn draw_picker(frame: &mut Frame, area: Rect, app: &mut App) {
+    let [input_area, list_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+
+    frame.render_widget(
+        Paragraph::new(format!("{}▏", app.picker.query))
+            .block(Block::default().borders(Borders::ALL).title(" Station ")),
+        input_area,
+    );
+
+    let items: Vec<ListItem> = app
+        .picker
+        .matches
+        .iter()
+        .map(|station| ListItem::new(station.name.clone()))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {} matches ", app.picker.matches.len())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("▶ ");
+    frame.render_stateful_widget(list, list_area, &mut app.picker.state);
+}
+
+fn draw_date(frame: &mut Frame, area: Rect, app: &App) {
+    let today = Local::now().date_naive();
+    let relative = match (app.date - today).num_days() {
+        0 => " (today)",
+        1 => " (tomorrow)",
+        _ => "",
+    };
+
+    let text = vec![
+        Line::from(""),
+        Line::from(format!(
+            "  📅 Date:       ◀ {} {:?}{} ▶",
+            format_date(&app.date),
+            app.date.weekday(),
+            relative
+        )),
+        Line::from(""),
+        Line::from(format!("  🕐 Start hour: ▲ {:02}:00 ▼", app.hour)),
+    ];
+    frame.render_widget(
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL).title(" When? ")),
+        area,
+    );
+}
+
+fn draw_timetable(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(timetable) = &app.timetable else {
+        return;
+    };
+
+    let header = Row::new(vec!["Start", "End", "Duration", "Trains", "Transfers"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = timetable
+        .journeys
+        .iter()
+        .enumerate()
+        .map(|(index, journey)| {
+            let row = Row::new(journey_cells(journey));
+            if Some(index) == app.next_departure {
+                row.style(
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let title = format!(
+        " {} · {} journeys{} ",
+        format_date(&app.date),
+        timetable.journeys.len(),
+        if app.next_departure.is_some() {
+            " · next departure in green"
+        } else {
+            ""
+        }
+    );
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(9),
+            Constraint::Length(20),
+            Constraint::Min(20),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, area, &mut app.table_state);
+}
+
+fn journey_cells(journey: &Journey) -> Vec<Cell<'static>> {
+    let transfers: Vec<String> = journey.legs[..journey.transfers()]
+        .iter()
+        .zip(journey.waits.iter())
+        .map(|(leg, wait)| format!("{} ({})", leg.destination, wait))
+        .collect();
+
+    vec![
+        Cell::from(journey.departure().to_string()),
+        Cell::from(journey.arrival_label()),
+        Cell::from(journey.duration.clone()),
+        Cell::from(journey.trains()),
+        Cell::from(transfers.join(", ")),
+    ]
+}
@@ -0,0 +1,194 @@
+use chrono::NaiveDate;
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, parse_minutes},
+    rodalies::{
+        filter::{apply_filter, JourneyFilter},
+        risk::{assess_risks, TIGHT_TRANSFER},
+        station::resolve_stations,
+        timetable::{get_timetable, Journey, Timetable},
+    },
+};
+
+/// The minimum stop time at a via station when none is given, enough to change trains.
+const DEFAULT_STOP: i64 = 5;
+
+/// A station to stop at on the way, given as `STATION[=STOP]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Via {
+    /// The station ID or name.
+    pub station: String,
+    /// The minimum minutes to stay at the station before taking the next train.
+    pub stop: i64,
+}
+
+/// Given a container of CLI args, it processes the repeatable `via` argument.
+pub fn parse_via(args: &ArgMatches) -> Result<Vec<Via>, Box<dyn Error>> {
+    let values = match args.try_get_many::<String>("via") {
+        Ok(Some(values)) => values,
+        _ => return Ok(Vec::new()),
+    };
+    values
+        .map(|value| match value.split_once('=') {
+            Some((station, stop)) => Ok(Via {
+                station: station.trim().to_string(),
+                stop: parse_minutes(stop)?,
+            }),
+            None => Ok(Via {
+                station: value.trim().to_string(),
+                stop: DEFAULT_STOP,
+            }),
+        })
+        .collect()
+}
+
+/// Formats minutes as the journeys' duration text (`HH:MM`).
+fn format_duration(minutes: i64) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Joins two journeys of the date, the second one departing from where the first one arrives.
+fn join_journeys(first: &Journey, second: &Journey, date: NaiveDate) -> Option<Journey> {
+    let wait = (second.departure_at(date)? - first.arrival_at(date)?).num_minutes();
+    let mut legs = first.legs.clone();
+    legs.extend(second.legs.iter().cloned());
+    let mut waits = first.waits.clone();
+    waits.push(format!("{} min", wait));
+    waits.extend(second.waits.iter().cloned());
+
+    let mut journey = Journey {
+        duration: format_duration(
+            (second.arrival_at(date)? - first.departure_at(date)?).num_minutes(),
+        ),
+        legs,
+        waits,
+        risks: Vec::new(),
+    };
+    journey.risks = assess_risks(&journey, TIGHT_TRANSFER);
+    Some(journey)
+}
+
+/// Stitches the timetables of consecutive segments of the date into whole journeys.
+///
+/// Each journey of the first segment takes the earliest connection departing after the stop time at each via station.
+/// When several journeys reach the destination at the same time, only the latest departing one is kept.
+pub fn stitch_journeys(segments: &[Timetable], stops: &[i64], date: NaiveDate) -> Vec<Journey> {
+    let mut journeys: Vec<Journey> = Vec::new();
+    let first = match segments.first() {
+        Some(first) => first,
+        None => return journeys,
+    };
+
+    for start in first.journeys.iter() {
+        let mut journey = Some(start.clone());
+        for (segment, stop) in segments.iter().skip(1).zip(stops) {
+            journey = journey.and_then(|journey| {
+                // connections are only searched on the date, so none is found past midnight
+                let ready = journey.arrival_at(date)? + chrono::Duration::minutes(*stop);
+                let connection = segment.journeys.iter().find(|next| {
+                    next.departure_at(date)
+                        .is_some_and(|departure| departure >= ready)
+                })?;
+                join_journeys(&journey, connection, date)
+            });
+        }
+
+        if let Some(journey) = journey {
+            journeys.retain(|previous| previous.arrival_at(date) != journey.arrival_at(date));
+            journeys.push(journey);
+        }
+    }
+
+    journeys.sort_by_key(|journey| journey.departure_at(date));
+    journeys
+}
+
+/// Searches the timetable of the trip stopping at the via stations, by chaining a search per segment.
+///
+/// The journeys are returned filtered, to be displayed as any other timetable.
+pub async fn search_via(
+    client: &Client,
+    from: String,
+    to: String,
+    via: Vec<Via>,
+    date: NaiveDate,
+    filter: &JourneyFilter,
+) -> Result<Timetable, Box<dyn Error>> {
+    let mut values = vec![from];
+    values.extend(via.iter().map(|via| via.station.clone()));
+    values.push(to);
+    let stations = resolve_stations(client, &values).await?;
+
+    println!(
+        "🧩 Chaining {} searches through {}",
+        stations.len() - 1,
+        via.iter()
+            .map(|via| format!("'{}' ({} min stop)", via.station, via.stop))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let mut segments = Vec::new();
+    for pair in stations.windows(2) {
+        segments.push(
+            get_timetable(
+                client,
+                pair[0].id.clone(),
+                pair[1].id.clone(),
+                format_date(&date),
+                "00".to_string(),
+            )
+            .await?,
+        );
+    }
+
+    let stops: Vec<i64> = via.iter().map(|via| via.stop).collect();
+    let journeys = stitch_journeys(&segments, &stops, date);
+    if journeys.is_empty() {
+        return Err("🚨 No journeys found connecting all the via stations on the same day".into());
+    }
+
+    let last = &segments[segments.len() - 1];
+    let mut timetable = Timetable {
+        transfers: journeys
+            .iter()
+            .map(|journey| journey.transfers())
+            .max()
+            .unwrap_or_default(),
+        journeys,
+        min_temp: last.min_temp.clone(),
+        max_temp: last.max_temp.clone(),
+    };
+    apply_filter(&mut timetable, filter)?;
+
+    Ok(timetable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    #[test]
+    fn test_stitch_journeys_respects_stop_time() {
+        let timetable = fixture_timetable();
+        let segments = vec![timetable.clone(), timetable];
+        let date = fixture_date();
+
+        // 06:04 arrives at 08:22, so the next connection is 21:19, and 21:19 arrives too late for any
+        let journeys = stitch_journeys(&segments, &[5], date);
+        assert_eq!(journeys.len(), 1);
+        let journey = &journeys[0];
+        assert_eq!(journey.departure(), "06:04");
+        assert_eq!(journey.arrival(), "23:17");
+        assert_eq!(journey.duration, "17:13");
+        assert_eq!(journey.transfers(), 2);
+        assert_eq!(journey.waits, vec!["5 min", "777 min"]);
+        assert_eq!(format_duration(138), "02:18");
+
+        assert!(stitch_journeys(&segments, &[24 * 60], date).is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use clap::ArgMatches;
+use prettytable::{color, Attr, Cell, Row, Table};
+use ratatui::crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
+use std::{
+    error::Error,
+    io::{stdout, Write},
+    time::{Duration, Instant},
+};
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, init_results_table},
+    rodalies::{
+        filter::JourneyFilter,
+        next::{dated_departures, format_minutes_left, get_next_departures, NextDeparture},
+        notify::{parse_notify, run_notify_command, NotifyConfig},
+        timetable::{get_timetable, parse_time},
+        trip::Trip,
+    },
+};
+
+/// What is being watched.
+pub enum WatchMode {
+    /// The next `count` departures from now on.
+    Next { count: usize },
+    /// The whole timetable of the given date, from the given hour (`HH`).
+    Timetable { date: NaiveDate, hour: String },
+}
+
+/// The watch settings given by the CLI args.
+pub struct WatchConfig {
+    /// How often the departures are requested again.
+    pub interval: Duration,
+    /// How many minutes before the chosen departure the terminal bell rings, if any.
+    pub bell: Option<i64>,
+    /// The chosen departure time, the next one if not given.
+    pub departure: Option<NaiveTime>,
+    /// The reminder command to run before the chosen departure, if any.
+    pub notify: Option<NotifyConfig>,
+}
+
+/// Given a container of CLI args, it processes the `departure` argument.
+pub fn parse_departure(args: &ArgMatches) -> Result<Option<NaiveTime>, Box<dyn Error>> {
+    match args.try_get_one::<String>("departure").ok().flatten() {
+        Some(departure) => match parse_time(departure) {
+            Some(departure) => Ok(Some(departure)),
+            None => Err("🚨 Please, specify the departure time as HH:MM".into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Given a container of CLI args, it processes the `watch`, `bell`, `departure` and reminder arguments.
+pub fn parse_watch(args: &ArgMatches) -> Result<Option<WatchConfig>, Box<dyn Error>> {
+    let interval = match args.try_get_one::<u64>("watch") {
+        Ok(Some(interval)) => *interval,
+        _ => return Ok(None),
+    };
+    if interval == 0 {
+        return Err("🚨 Please, specify a watch interval of at least one second".into());
+    }
+
+    Ok(Some(WatchConfig {
+        interval: Duration::from_secs(interval),
+        bell: args.get_one::<i64>("bell").copied(),
+        departure: parse_departure(args)?,
+        notify: parse_notify(args)?,
+    }))
+}
+
+/// Requests the departures being watched, passing the filters.
+pub async fn get_watched_departures(
+    client: &Client,
+    trip: &Trip,
+    mode: &WatchMode,
+    now: NaiveDateTime,
+    filter: &JourneyFilter,
+) -> Result<Vec<NextDeparture>, Box<dyn Error>> {
+    match mode {
+        WatchMode::Next { count } => get_next_departures(client, trip, now, *count, filter).await,
+        WatchMode::Timetable { date, hour } => {
+            let timetable = get_timetable(
+                client,
+                trip.from.id.clone(),
+                trip.to.id.clone(),
+                format_date(date),
+                hour.clone(),
+            )
+            .await?;
+            let mut departures = dated_departures(&timetable.journeys, *date);
+            departures.retain(|departure| filter.matches(&departure.journey));
+            Ok(departures)
+        }
+    }
+}
+
+/// Keeps displaying the watched departures in place, with a countdown, until interrupted.
+///
+/// The same client is reused, and departures are only requested again once older than the interval.
+/// Trains that already left are dimmed, and the terminal bell rings once for the chosen departure.
+pub async fn watch_departures(
+    client: &Client,
+    trip: &Trip,
+    mode: WatchMode,
+    config: WatchConfig,
+    filter: &JourneyFilter,
+) -> Result<(), Box<dyn Error>> {
+    let mut cached: Option<(Instant, Vec<NextDeparture>)> = None;
+    let mut error: Option<String> = None;
+    let mut rung: Option<NaiveDateTime> = None;
+    let mut notified: Option<NaiveDateTime> = None;
+
+    loop {
+        let now = Local::now().naive_local();
+
+        let outdated = match &cached {
+            Some((fetched, _)) => fetched.elapsed() >= config.interval,
+            None => true,
+        };
+        if outdated {
+            match get_watched_departures(client, trip, &mode, now, filter).await {
+                Ok(departures) => {
+                    cached = Some((Instant::now(), departures));
+                    error = None;
+                }
+                Err(fetch_error) => {
+                    // keep showing the last departures, and try again on the next interval
+                    let departures = cached.take().map(|(_, d)| d).unwrap_or_default();
+                    cached = Some((Instant::now(), departures));
+                    error = Some(fetch_error.to_string());
+                }
+            }
+        }
+
+        let departures = cached.as_ref().map(|(_, d)| d.as_slice()).unwrap_or(&[]);
+        let chosen = chosen_departure(departures, now, config.departure);
+
+        let (from, to) = match departures.first() {
+            Some(first) => (
+                first.journey.legs[0].origin.as_str(),
+                first.journey.legs[first.journey.transfers()]
+                    .destination
+                    .as_str(),
+            ),
+            None => (trip.from.name.as_str(), trip.to.name.as_str()),
+        };
+
+        execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+        println!(
+            "👀 Watching trains from '{}' to '{}', refreshing every {}s ({} now). Press Ctrl+C to stop.",
+            from,
+            to,
+            config.interval.as_secs(),
+            now.format("%H:%M")
+        );
+        if let Some(error) = &error {
+            println!("{}", error);
+        }
+        build_watch_table(departures, now, chosen).printstd();
+
+        if let (Some(bell), Some(chosen)) = (config.bell, chosen) {
+            let minutes = chosen.minutes_left(now);
+            if minutes >= 0 && minutes <= bell && rung != Some(chosen.departure) {
+                rung = Some(chosen.departure);
+                print!("\x07");
+                println!(
+                    "🔔 The {} train leaves in {}!",
+                    chosen.journey.departure(),
+                    format_minutes_left(minutes)
+                );
+            }
+        }
+        stdout().flush()?;
+
+        if let (Some(notify), Some(chosen)) = (&config.notify, chosen) {
+            let minutes = chosen.minutes_left(now);
+            if minutes >= 0 && minutes <= notify.before && notified != Some(chosen.departure) {
+                notified = Some(chosen.departure);
+                if let Err(error) = run_notify_command(notify, chosen, now).await {
+                    println!("{}", error);
+                }
+            }
+        }
+
+        // sleep until the countdown changes, or until the departures are due to be requested again
+        let to_next_minute = Duration::from_secs(60 - u64::from(now.second()));
+        let to_refresh = cached.as_ref().map_or(Duration::ZERO, |(fetched, _)| {
+            config.interval.saturating_sub(fetched.elapsed())
+        });
+        tokio::time::sleep(to_next_minute.min(to_refresh).max(Duration::from_secs(1))).await;
+    }
+}
+
+/// Returns the chosen departure: the one at the given time, or the next one from `now` on.
+pub fn chosen_departure(
+    departures: &[NextDeparture],
+    now: NaiveDateTime,
+    time: Option<NaiveTime>,
+) -> Option<&NextDeparture> {
+    match time {
+        Some(time) => departures
+            .iter()
+            .find(|departure| departure.departure.time() == time && departure.departure >= now),
+        None => departures
+            .iter()
+            .find(|departure| departure.departure >= now),
+    }
+}
+
+/// Builds the table of the watched departures, dimming the trains that already left.
+pub fn build_watch_table(
+    departures: &[NextDeparture],
+    now: NaiveDateTime,
+    chosen: Option<&NextDeparture>,
+) -> Table {
+    let mut results_table = init_results_table();
+    results_table.set_titles(Row::new(vec![
+        Cell::new("Start"),
+        Cell::new("End"),
+        Cell::new("Duration"),
+        Cell::new("Train"),
+        Cell::new("Leaves in"),
+    ]));
+
+    for departure in departures.iter() {
+        let left = departure.departure < now;
+        let leaves_in = if left {
+            "left".to_string()
+        } else {
+            format_minutes_left(departure.minutes_left(now))
+        };
+        let cells = vec![
+            Cell::new(departure.journey.departure()),
+            Cell::new(&departure.journey.arrival_label()),
+            Cell::new(&departure.journey.duration),
+            Cell::new(&departure.journey.trains()),
+            Cell::new(&leaves_in).style_spec("r"),
+        ];
+        let cells = cells
+            .into_iter()
+            .map(|cell| {
+                if left {
+                    cell.with_style(Attr::Dim)
+                        .with_style(Attr::ForegroundColor(color::BRIGHT_BLACK))
+                } else if chosen == Some(departure) {
+                    cell.with_style(Attr::Bold)
+                } else {
+                    cell
+                }
+            })
+            .collect();
+        results_table.add_row(Row::new(cells));
+    }
+
+    results_table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    #[test]
+    fn test_build_watch_table_marks_left_trains() {
+        let timetable = fixture_timetable();
+        let date = fixture_date();
+        let now = date.and_hms_opt(7, 0, 0).unwrap();
+        let departures = dated_departures(&timetable.journeys, date);
+
+        let chosen = chosen_departure(&departures, now, None);
+        assert_eq!(chosen.unwrap().journey.departure(), "21:19");
+        assert!(chosen_departure(&departures, now, parse_time("06:04")).is_none());
+
+        let table = build_watch_table(&departures, now, chosen);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table[0][4].get_content(), "left");
+        assert_eq!(table[1][4].get_content(), "14 h 19 min");
+    }
+}
@@ -0,0 +1,252 @@
+use chrono::{Days, NaiveDate, NaiveTime, Timelike};
+use clap::ArgMatches;
+use prettytable::{color, Attr, Cell, Row, Table};
+use std::{error::Error, io::stdout, time::Duration};
+use surf::Client;
+
+use crate::{
+    config::cli::{format_date, init_results_table, parse_query_date},
+    rodalies::{
+        matrix::parse_window,
+        timetable::{get_dated_timetables, parse_time, Timetable, TimetableError},
+        trip::parse_trip_input,
+    },
+};
+
+/// How many consecutive days are compared.
+const WEEK_DAYS: u64 = 7;
+
+/// A train departing at the same time with the same lines on some days of the week.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeekTrain {
+    /// The departure time (`HH:MM`).
+    pub departure: String,
+    /// The train lines taken, in order.
+    pub trains: String,
+    /// Whether it runs on each day of the week.
+    pub days: Vec<bool>,
+}
+
+impl WeekTrain {
+    /// Tells whether it runs on every day whose timetable is known.
+    pub fn runs_every_day(&self, known: &[bool]) -> bool {
+        self.days
+            .iter()
+            .zip(known)
+            .all(|(runs, known)| *runs || !*known)
+    }
+}
+
+/// Lines up the trains of every day departing within the window, sorted by departure time.
+///
+/// A day without timetable, as when it could not be requested, has no trains.
+pub fn build_week_trains(
+    timetables: &[Option<Timetable>],
+    after: NaiveTime,
+    before: NaiveTime,
+) -> Vec<WeekTrain> {
+    let mut trains: Vec<WeekTrain> = Vec::new();
+    for (day, timetable) in timetables.iter().enumerate() {
+        let journeys = timetable
+            .iter()
+            .flat_map(|timetable| timetable.journeys.iter());
+        for journey in journeys {
+            let within = parse_time(journey.departure())
+                .is_some_and(|departure| departure >= after && departure <= before);
+            if !within {
+                continue;
+            }
+
+            let (departure, lines) = (journey.departure(), journey.trains());
+            let position = trains
+                .iter()
+                .position(|train| train.departure == departure && train.trains == lines);
+            match position {
+                Some(position) => trains[position].days[day] = true,
+                None => {
+                    let mut days = vec![false; timetables.len()];
+                    days[day] = true;
+                    trains.push(WeekTrain {
+                        departure: departure.to_string(),
+                        trains: lines,
+                        days,
+                    });
+                }
+            }
+        }
+    }
+
+    trains.sort_by_key(|train| (parse_time(&train.departure), train.trains.clone()));
+    trains
+}
+
+/// Builds the table of the trains side by side per day, highlighting the ones that only run on some days.
+pub fn build_week_table(dates: &[NaiveDate], known: &[bool], trains: &[WeekTrain]) -> Table {
+    let mut results_table = init_results_table();
+    let mut title_cells = vec![Cell::new("Train")];
+    title_cells.extend(dates.iter().zip(known).map(|(date, known)| {
+        let title = date.format("%a %d/%m").to_string();
+        Cell::new(&if *known {
+            title
+        } else {
+            format!("{} ?", title)
+        })
+    }));
+    title_cells.push(Cell::new("Days"));
+    results_table.set_titles(Row::new(title_cells));
+
+    for train in trains.iter() {
+        let mut cells = vec![Cell::new(&train.trains)];
+        cells.extend(train.days.iter().map(|runs| {
+            Cell::new(if *runs { train.departure.as_str() } else { "" }).style_spec("c")
+        }));
+        let runs = train.days.iter().filter(|runs| **runs).count();
+        cells.push(Cell::new(&format!("{}/{}", runs, train.days.len())).style_spec("r"));
+
+        let cells = if train.runs_every_day(known) {
+            cells
+        } else {
+            cells
+                .into_iter()
+                .map(|cell| cell.with_style(Attr::ForegroundColor(color::YELLOW)))
+                .collect()
+        };
+        results_table.add_row(Row::new(cells));
+    }
+
+    results_table
+}
+
+/// Displays the trains of the trip given by the CLI args on 7 consecutive days, side by side.
+pub async fn search_week(client: &Client, args: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let start = parse_query_date(args.get_one::<String>("date").map(|date| date.as_str()))?;
+    let (after, before) = parse_window(args)?;
+    let output = args
+        .get_one::<String>("output")
+        .map_or("table", |output| output.as_str());
+    let concurrency = *args.get_one::<u64>("concurrency").unwrap_or(&2) as usize;
+    let delay = Duration::from_millis(*args.get_one::<u64>("delay").unwrap_or(&500));
+    let trip = parse_trip_input(client, args).await?;
+
+    let dates: Vec<NaiveDate> = (0..WEEK_DAYS)
+        .filter_map(|day| start.checked_add_days(Days::new(day)))
+        .collect();
+    if output == "table" {
+        println!(
+            "🗓️ Comparing the trains from '{}' to '{}' from {} to {}",
+            trip.from.name,
+            trip.to.name,
+            format_date(&dates[0]),
+            format_date(&dates[dates.len() - 1])
+        );
+    }
+
+    let queries: Vec<_> = dates.iter().map(|date| (trip.clone(), *date)).collect();
+    let hour = format!("{:02}", after.hour());
+    let results = get_dated_timetables(client, &queries, &hour, concurrency, delay).await;
+
+    let mut timetables = Vec::new();
+    for (date, result) in dates.iter().zip(results) {
+        timetables.push(match result {
+            Ok(timetable) => Some(timetable),
+            // the site reports when there are no trains on a day, which is worth comparing too
+            Err(TimetableError::NoTrains(..)) => Some(Timetable::default()),
+            Err(TimetableError::Failed(error)) => {
+                if output == "table" {
+                    println!(
+                        "🕵️ Could not get the timetable of {}: {}",
+                        format_date(date),
+                        error
+                    );
+                }
+                None
+            }
+        });
+    }
+    let known: Vec<bool> = timetables
+        .iter()
+        .map(|timetable| timetable.is_some())
+        .collect();
+    if !known.contains(&true) {
+        return Err("🚨 No timetables found for any day of the week".into());
+    }
+
+    let trains = build_week_trains(&timetables, after, before);
+    let table = build_week_table(&dates, &known, &trains);
+    match output {
+        "csv" => {
+            table.to_csv(stdout())?;
+        }
+        _ => {
+            table.printstd();
+            let partial = trains
+                .iter()
+                .filter(|train| !train.runs_every_day(&known))
+                .count();
+            if partial > 0 {
+                println!(
+                    "📢 {} of {} trains only run on some days, they are highlighted",
+                    partial,
+                    trains.len()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rodalies::timetable::{fixture_date, fixture_timetable};
+
+    /// Returns the fixture on the first day, its evening journey only on the second day and no
+    /// answer on the third one.
+    fn week_timetables() -> Vec<Option<Timetable>> {
+        let timetable = fixture_timetable();
+        let weekend = Timetable {
+            journeys: vec![timetable.journeys[1].clone()],
+            ..timetable.clone()
+        };
+        vec![Some(timetable), Some(weekend), None]
+    }
+
+    fn time(time: &str) -> NaiveTime {
+        parse_time(time).unwrap()
+    }
+
+    #[test]
+    fn test_build_week_trains() {
+        let timetables = week_timetables();
+        let trains = build_week_trains(&timetables, time("00:00"), time("23:59"));
+        assert_eq!(trains.len(), 2);
+        assert_eq!(trains[0].departure, "06:04");
+        assert_eq!(trains[0].trains, "R11 → R2");
+        assert_eq!(trains[0].days, vec![true, false, false]);
+        assert_eq!(trains[1].days, vec![true, true, false]);
+
+        let trains = build_week_trains(&timetables, time("07:00"), time("23:59"));
+        assert_eq!(trains.len(), 1);
+    }
+
+    #[test]
+    fn test_runs_every_known_day() {
+        let trains = build_week_trains(&week_timetables(), time("00:00"), time("23:59"));
+        let known = vec![true, true, false];
+        assert!(!trains[0].runs_every_day(&known));
+        assert!(trains[1].runs_every_day(&known));
+    }
+
+    #[test]
+    fn test_build_week_table() {
+        let trains = build_week_trains(&week_timetables(), time("07:00"), time("23:59"));
+        let known = vec![true, true, false];
+        let start = fixture_date();
+        let dates: Vec<NaiveDate> = (0..3).map(|day| start + Days::new(day)).collect();
+        let table = build_week_table(&dates, &known, &trains);
+        assert_eq!(table[0][1].get_content(), "21:19");
+        assert_eq!(table[0][3].get_content(), "");
+        assert_eq!(table[0][4].get_content(), "2/3");
+    }
+}
@@ -43,4 +43,195 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn cli_fails_when_tui_has_trip_args() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["--tui", "-f", "79300", "-t", "71701"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_listing_empty_history() -> Result<(), Box<dyn std::error::Error>> {
+        let data_dir = std::env::temp_dir().join("rodalies-cli-test-empty-history");
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.env("RODALIES_CLI_DATA_DIR", &data_dir)
+            .arg("history")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "There are no searches on the history yet",
+            ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_success_when_listing_history() -> Result<(), Box<dyn std::error::Error>> {
+        let data_dir = std::env::temp_dir().join("rodalies-cli-test-history");
+        std::fs::create_dir_all(&data_dir)?;
+        std::fs::write(
+            data_dir.join("history.json"),
+            r#"[{"from":{"id":"79300","name":"Girona"},"to":{"id":"79202","name":"Sils"},"date":"tomorrow","hour":"08"}]"#,
+        )?;
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.env("RODALIES_CLI_DATA_DIR", &data_dir)
+            .arg("history")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Girona"))
+            .stdout(predicate::str::contains("tomorrow"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_next_favourite_does_not_exist() -> Result<(), Box<dyn std::error::Error>> {
+        let data_dir = std::env::temp_dir().join("rodalies-cli-test-no-favourites");
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.env("RODALIES_CLI_DATA_DIR", &data_dir)
+            .args(["next", "work"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "There is no favourite trip named 'work'",
+            ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_prints_only_waybar_json_when_failing() -> Result<(), Box<dyn std::error::Error>> {
+        let data_dir = std::env::temp_dir().join("rodalies-cli-test-no-favourites");
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.env("RODALIES_CLI_DATA_DIR", &data_dir)
+            .args(["next", "work", "--output", "waybar"])
+            .assert()
+            .success()
+            .stdout(
+                predicate::str::starts_with("{")
+                    .and(predicate::str::contains(r#""class":"error""#)),
+            )
+            .stdout(predicate::str::contains("Rodalies CLI").not());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_answers_only_json_rpc_on_stdio() -> Result<(), Box<dyn std::error::Error>> {
+        // unlike the std one, assert_cmd's command can write to stdin
+        let mut cmd = assert_cmd::Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["serve", "--stdio"])
+            .write_stdin("{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"bookTrain\"}\n")
+            .assert()
+            .success()
+            .stdout(
+                predicate::str::starts_with("{").and(predicate::str::contains(r#""code":-32601"#)),
+            )
+            .stdout(predicate::str::contains("Rodalies CLI").not());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_batch_line_is_wrong() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["batch", "--output", "json"])
+            .write_stdin("79300, 79202\n1, 2, 3, 4, 5\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Please, write line 2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_home_time_is_wrong() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["last", "-f", "79300", "-t", "71701", "--home-time", "25:00"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("going home time as HH:MM"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_diff_against_date_is_wrong() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["diff", "-f", "79300", "-t", "71701", "--against", "someday"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("specify the date as dd/mm/yyyy"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_reminder_has_no_command() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.env_remove("RODALIES_CLI_NOTIFY_CMD")
+            .args(["next", "-f", "79300", "-t", "71701", "--notify-before", "5"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "specify the reminder command with --notify-cmd",
+            ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_via_and_arrive_by_are_combined() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["-f", "79300", "-t", "71701", "--via", "71801"])
+            .args(["--arrive-by", "09:00"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_several_origins_arrive_by() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["-f", "79300", "-f", "71801", "-t", "71701"])
+            .args(["--arrive-by", "09:00"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "search several origins or destinations without '--arrive-by'",
+            ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fails_when_next_filter_is_wrong() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("rodalies-cli").unwrap();
+
+        cmd.args(["next", "-f", "79300", "-t", "71701", "--max-wait", "soon"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("specify 'soon' as minutes"));
+
+        Ok(())
+    }
 }